@@ -1,340 +1,4561 @@
-use bed::feature::RecordBuf;
 use bed::io::writer::Writer as BedWriter;
+pub use bit_vec::BitVec;
 use bstr::ByteSlice;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use log::{debug, info, warn};
+use md5::Md5;
 use noodles_bed as bed;
-use noodles_core::Position;
+use noodles_fasta as fasta;
 use noodles_fasta::Record as FastaRecord;
+use rayon::prelude::*;
 use regex::Regex;
+use regex::RegexBuilder;
+use serde::Deserialize;
 use serde::Serialize;
 use sha2::Digest;
 use sha2::Sha256;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
 use std::fs::File;
 use std::path::Path;
+use std::path::PathBuf;
+use std::io::BufRead;
 use std::io::BufWriter;
+use std::io::IntoInnerError;
+use std::io::Write;
 use std::result::Result;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessError {
+    InvalidRegex(String),
+    NonUtf8Name(String),
+    UnexpectedBase { base: u8, sequence: String },
+    UnexpectedSoftMask { position: usize, sequence: String },
+    DuplicateName(String),
+    ReadError(String),
+    WriteError(String),
+    UnequalLengths { sequence: String, length: usize, expected_length: usize },
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessError::InvalidRegex(regex) => write!(f, "Invalid regular expression: '{}'", regex),
+            ProcessError::NonUtf8Name(name) => write!(f, "Failed to convert record name to string: '{}'", name),
+            ProcessError::UnexpectedBase { base, sequence } => {
+                write!(f, "Unexpected base: '{}' in sequence '{}'.", *base as char, sequence)
+            }
+            ProcessError::UnexpectedSoftMask { position, sequence } => write!(
+                f,
+                "Unexpected soft-masked (lowercase) base at position {} (0-based) in sequence '{}'.",
+                position, sequence
+            ),
+            ProcessError::DuplicateName(name) => write!(f, "Duplicate sequence name: '{}'.", name),
+            ProcessError::ReadError(message) => write!(f, "Failed to read FASTA record: {}", message),
+            ProcessError::WriteError(message) => write!(f, "Failed to write output file: {}", message),
+            ProcessError::UnequalLengths { sequence, length, expected_length } => write!(
+                f,
+                "All sequences must have the same length, but sequence '{}' has {} bp instead of {} bp.",
+                sequence, length, expected_length
+            ),
+        }
+    }
+}
+
+impl Error for ProcessError {}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum ChecksumKind {
+    #[default]
+    Sha256,
+    Md5,
+    Both,
+    Blake3,
+}
+
+/// The expected alphabet of the sequences: DNA (with `T`), RNA (with `U` instead of `T`), or both.
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum Alphabet {
+    #[default]
+    Dna,
+    Rna,
+    Auto,
+}
+
+impl Alphabet {
+    /// Maps a base onto the DNA alphabet, so that `U`/`u` are handled like `T`/`t` for RNA. As `T`/`t` are not
+    /// expected in RNA, they are mapped onto `U`/`u` instead, which are unexpected in DNA.
+    fn dna_base(self, base: u8) -> u8 {
+        match (self, base) {
+            (Alphabet::Rna | Alphabet::Auto, b'U') => b'T',
+            (Alphabet::Rna | Alphabet::Auto, b'u') => b't',
+            (Alphabet::Rna, b'T') => b'U',
+            (Alphabet::Rna, b't') => b'u',
+            _ => base,
+        }
+    }
+}
+
+/// The number of columns of the BED files, see [write_bed_record].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum BedColumns {
+    #[default]
+    #[value(name = "3")]
+    Three,
+    #[value(name = "4")]
+    Four,
+    #[value(name = "5")]
+    Five,
+}
+
+/// How to handle records whose name has already been seen before: fail on them, write the output files of the n-th
+/// record with the same name to `<name>.<n>.*`, or append their regions to the output files of the first record with
+/// that name (see [merge_sequence_statistics] for merging their statistics).
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum DuplicatePolicy {
+    #[default]
+    Error,
+    Rename,
+    Merge,
+}
+
+/// A checksum algorithm that is fed with the bytes of a sequence.
+pub trait Checksum {
+    fn update(&mut self, bytes: &[u8]);
+    /// Returns the hex-encoded checksum.
+    fn finalize(self: Box<Self>) -> String;
+}
+
+#[derive(Default)]
+pub struct Sha256Checksum(Sha256);
+
+impl Checksum for Sha256Checksum {
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finalize(self: Box<Self>) -> String {
+        format!("{:x}", self.0.finalize())
+    }
+}
+
+#[derive(Default)]
+pub struct Md5Checksum(Md5);
+
+impl Checksum for Md5Checksum {
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finalize(self: Box<Self>) -> String {
+        format!("{:x}", self.0.finalize())
+    }
+}
+
+#[derive(Default)]
+pub struct Blake3Checksum(blake3::Hasher);
+
+impl Checksum for Blake3Checksum {
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finalize(self: Box<Self>) -> String {
+        self.0.finalize().to_hex().to_string()
+    }
+}
+
+/// The checksum algorithms, each stored in its own field of [SequenceStatistics].
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ChecksumAlgorithm {
+    Sha256,
+    Md5,
+    Blake3,
+}
+
+impl ChecksumAlgorithm {
+    fn checksum(self) -> Box<dyn Checksum> {
+        match self {
+            ChecksumAlgorithm::Sha256 => Box::new(Sha256Checksum::default()),
+            ChecksumAlgorithm::Md5 => Box::new(Md5Checksum::default()),
+            ChecksumAlgorithm::Blake3 => Box::new(Blake3Checksum::default()),
+        }
+    }
+}
+
+impl ChecksumKind {
+    fn algorithms(self) -> &'static [ChecksumAlgorithm] {
+        match self {
+            ChecksumKind::Sha256 => &[ChecksumAlgorithm::Sha256],
+            ChecksumKind::Md5 => &[ChecksumAlgorithm::Md5],
+            ChecksumKind::Both => &[ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Md5],
+            ChecksumKind::Blake3 => &[ChecksumAlgorithm::Blake3],
+        }
+    }
+}
+
+/// Computes the selected checksum(s) in a single pass over the sequence.
+struct SequenceHasher {
+    checksums: Vec<(ChecksumAlgorithm, Box<dyn Checksum>)>,
+}
+
+impl SequenceHasher {
+    fn new(checksum_kind: ChecksumKind) -> SequenceHasher {
+        let checksums = checksum_kind.algorithms().iter().map(|algorithm| (*algorithm, algorithm.checksum())).collect();
+        SequenceHasher { checksums }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for (_, checksum) in &mut self.checksums {
+            checksum.update(bytes);
+        }
+    }
+
+    /// Returns the checksums of the selected algorithms.
+    fn finalize(self) -> Checksums {
+        let mut checksums = Checksums::default();
+        for (algorithm, checksum) in self.checksums {
+            let value = Some(checksum.finalize());
+            match algorithm {
+                ChecksumAlgorithm::Sha256 => checksums.sha256 = value,
+                ChecksumAlgorithm::Md5 => checksums.md5 = value,
+                ChecksumAlgorithm::Blake3 => checksums.blake3 = value,
+            }
+        }
+        checksums
+    }
+}
+
+/// The hex-encoded checksums of a sequence (if selected).
+#[derive(Default)]
+struct Checksums {
+    sha256: Option<String>,
+    md5: Option<String>,
+    blake3: Option<String>,
+    canonical: Option<String>,
+}
+
+impl Checksums {
+    /// Empty checksums for the selected algorithms, e.g., for empty or merged sequences.
+    fn empty(checksum_kind: ChecksumKind) -> Checksums {
+        let algorithms = checksum_kind.algorithms();
+        let empty = |algorithm| algorithms.contains(&algorithm).then(String::new);
+        Checksums {
+            sha256: empty(ChecksumAlgorithm::Sha256),
+            md5: empty(ChecksumAlgorithm::Md5),
+            blake3: empty(ChecksumAlgorithm::Blake3),
+            canonical: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SequenceStatistics {
     pub sequence_name: String,
+    /// The description of the sequence, i.e., the text after the name in the FASTA header (if any).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Path of the FASTA file the sequence was read from (empty if the statistics were not computed from a file).
+    pub source_file: String,
     pub non_masked_bases: usize,
     pub soft_masked_bases: usize,
     pub hard_masked_bases: usize,
     pub non_masked_ratio: f64,
     pub soft_masked_ratio: f64,
     pub hard_masked_ratio: f64,
+    /// Number of non-masked regions, i.e., maximal runs of non-masked bases (as in the non-masked BED file).
+    pub non_masked_region_count: usize,
+    /// Number of soft-masked regions, i.e., maximal runs of soft-masked bases.
+    pub soft_masked_region_count: usize,
+    /// Number of hard-masked regions, i.e., maximal runs of hard-masked bases (same as [Self::num_gaps]).
+    pub hard_masked_region_count: usize,
+    /// Mean length of the soft-masked regions, i.e., `soft_masked_bases / soft_masked_region_count` (`0.0` if there are
+    /// none), which is small if the masking is fragmented into many short intervals.
+    pub soft_mask_region_mean_length: f64,
+    /// Length of the longest soft-masked region.
+    pub soft_mask_region_max_length: usize,
+    /// Lengths of all soft-masked regions, in the order of their occurrence (if
+    /// [StatsOptions::soft_mask_region_lengths] is set).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soft_mask_region_lengths: Option<Vec<usize>>,
     pub gc_content: f64,
-    pub other_iupac_bases: usize,
+    /// Fraction of `G`/`C` bases (case-insensitive) among all `A`, `C`, `G`, and `T` bases, i.e., excluding `N`.
+    pub gc_content_no_n: f64,
+    /// Fraction of `G`/`C` bases among all soft- and hard-masked bases (`0.0` if there are none).
+    pub gc_content_masked: f64,
+    /// Fraction of `G`/`C` bases among all non-masked bases (`0.0` if there are none).
+    pub gc_content_unmasked: f64,
+    /// GC content with soft-masked bases weighted by `w` ([StatsOptions::softmask_weight]), i.e.,
+    /// `(gc_unmasked + w * gc_soft) / (length - soft_masked_bases + w * soft_masked_bases)`, where `gc_soft` is the
+    /// number of soft-masked `g`/`c` and `gc_unmasked` the number of all other `G`/`C` (`0.0` if the denominator is
+    /// zero). With `w = 1.0`, it is the same as [Self::gc_content] (unless [StatsOptions::iupac_aware] is set), and
+    /// `w = 0.0` excludes soft-masked bases. It is averaged weighted by sequence length when merging statistics.
+    pub gc_content_weighted: f64,
+    /// Fraction of `A`/`T` bases (case-insensitive) among all bases.
+    pub at_content: f64,
+    /// GC skew `(G - C) / (G + C)`, which is positive if there are more `G` than `C` (and `0.0` if there are neither).
+    pub gc_skew: f64,
+    /// AT skew `(A - T) / (A + T)`, which is positive if there are more `A` than `T` (and `0.0` if there are neither).
+    pub at_skew: f64,
+    /// End (0-based, exclusive) of the window at which the cumulative GC skew is minimal, which often marks the
+    /// replication terminus (if [StatsOptions::gc_skew_window] is set), see [write_gc_skew_bedgraph]. It is not set
+    /// for merged statistics.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gc_skew_min_position: Option<usize>,
+    /// End (0-based, exclusive) of the window at which the cumulative GC skew is maximal, which often marks the
+    /// replication origin (like [Self::gc_skew_min_position]).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gc_skew_max_position: Option<usize>,
+    /// Number of ambiguous IUPAC codes (`R`, `Y`, `S`, `W`, `K`, `M`, `B`, `D`, `H`, or `V`, case-insensitive), which
+    /// are only accepted with [StatsOptions::ignore_iupac] or [StatsOptions::iupac_aware].
+    pub iupac_ambiguous_bases: usize,
+    /// Number of any other unknown bytes, which are only accepted with [StatsOptions::ignore_iupac].
+    pub other_bases: usize,
+    pub a_count: usize,
+    pub c_count: usize,
+    pub g_count: usize,
+    pub t_count: usize,
+    pub n_count: usize,
+    /// Shannon entropy (in bits) of the distribution of `A`, `C`, `G`, and `T` (case-insensitive), ignoring all other bases.
+    pub shannon_entropy: f64,
+    /// Length of the longest run of the same nucleotide (`A`, `C`, `G`, or `T`, case-insensitive).
+    pub longest_homopolymer: usize,
+    /// Nucleotide of the first longest homopolymer run, or `.` if there is none.
+    pub longest_homopolymer_base: char,
+    /// Number of gaps, i.e., maximal runs of `N`/`n` (only `N` if [StatsOptions::split_n_case] is set).
+    pub num_gaps: usize,
+    /// Lengths of all gaps, in the order of their occurrence.
+    pub gap_lengths: Vec<usize>,
+    /// Length of the longest maximal run without gaps, e.g., of the largest contig within a scaffold.
+    pub longest_ungapped_run: usize,
+    /// Counts of all 16 dinucleotides (e.g., `CG`) in overlapping windows of size 2, case-insensitive and skipping pairs
+    /// with any other base than `A`, `C`, `G`, or `T` (if [StatsOptions::dinucleotides] is set).
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_sorted")]
+    pub dinucleotide_counts: Option<HashMap<String, usize>>,
+    /// Number of `CG` dinucleotides (if [StatsOptions::dinucleotides] is set).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpg_count: Option<usize>,
+    /// Observed/expected CpG ratio `cpg_count * (a + c + g + t) / (c * g)`, or `0.0` if there are no `C` or no `G`
+    /// (if [StatsOptions::dinucleotides] is set).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpg_observed_expected_ratio: Option<f64>,
+    /// Estimated melting temperature (in °C) of the sequence as an oligo, see [melting_temperature] (if
+    /// [StatsOptions::tm] is set and the sequence only consists of `A`, `C`, `G`, and `T`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tm_celsius: Option<f64>,
+    /// Number of bases, i.e., without the alignment gaps (if [StatsOptions::alignment_mode] is set).
     pub sequence_length: usize,
+    /// Number of alignment gaps (`-`), which are excluded from [Self::sequence_length] and all ratios (if
+    /// [StatsOptions::alignment_mode] is set).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gap_dash_bases: Option<usize>,
+    /// Number of alignment columns, i.e., [Self::sequence_length] plus [Self::gap_dash_bases] (if
+    /// [StatsOptions::alignment_mode] is set).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alignment_length: Option<usize>,
+    /// Hex-encoded SHA256 checksum of the sequence bytes (empty if not selected or [StatsOptions::no_checksum]). If
+    /// [StatsOptions::checksum_uppercase] is set, each byte is mapped with [u8::to_ascii_uppercase] before hashing,
+    /// i.e., `a-z` become `A-Z` and all other bytes are left as is.
     pub checksum_sha256: String,
+    /// Hex-encoded MD5 checksum of the sequence bytes (if selected), normalized like [Self::checksum_sha256].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum_md5: Option<String>,
+    /// Hex-encoded BLAKE3 checksum of the sequence bytes (if selected), normalized like [Self::checksum_sha256].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum_blake3: Option<String>,
+    /// Hex-encoded SHA256 checksum of the uppercase sequence or its reverse complement, whichever sorts first, so that
+    /// both strands have the same checksum (if [StatsOptions::canonical_checksum] is set), see [canonical_checksum].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum_canonical: Option<String>,
+    /// Wall-clock time (in milliseconds) spent processing the record, e.g., to find sequences that dominate the runtime
+    /// (if [StatsOptions::record_timings] is set). It is summed up when merging statistics.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub processing_ms: Option<f64>,
+    /// Positions (0-based) and bytes of the bases other than `ACGTN` (if [StatsOptions::report_unexpected] is set),
+    /// at most [MAX_REPORTED_UNEXPECTED_BASES]. Their total number is `iupac_ambiguous_bases + other_bases`.
+    #[serde(skip)]
+    pub unexpected_bases: Vec<(usize, u8)>,
+    /// Wall-clock time spent writing the regions of the sequence into the BED (and mask bedGraph) files.
+    #[serde(skip)]
+    pub bed_writing_time: Duration,
+    /// Paths of the per-sequence output files written for the sequence, along with their type (e.g., `bed`).
+    #[serde(skip)]
+    pub output_files: Vec<(PathBuf, &'static str)>,
 }
 
-pub fn process_fasta(
-    output_dir: Option<&Path>,
-    sequence_match_regex: &str,
-    ignore_iupac: bool,
-) -> impl Fn(&FastaRecord) -> Option<SequenceStatistics> {
-    move |record| process_fasta_record(record, output_dir, sequence_match_regex, ignore_iupac)
-}
+/// Maximum number of unexpected bases that are reported, see [SequenceStatistics::unexpected_bases].
+pub const MAX_REPORTED_UNEXPECTED_BASES: usize = 10_000;
 
-fn process_fasta_record(
-    record: &FastaRecord,
-    output_dir: Option<&Path>,
-    sequence_match_regex: &str,
-    ignore_iupac: bool,
-) -> Option<SequenceStatistics> {
-    let record_name: &str = record.definition().name().to_str().expect(
-        format!(
-            "Failed to convert record name to string: '{}'",
-            record.definition().name()
-        )
-        .as_str(),
-    );
+/// Version of the summary schema, to be increased whenever fields are added to or removed from [SequenceStatistics].
+pub const SUMMARY_SCHEMA_VERSION: u32 = 11;
 
-    // Ignore records that do not match the regex
-    let regex_matcher = Regex::new(ensure_full_match_regex(sequence_match_regex).as_str());
-    if regex_matcher.is_err() {
-        panic!("Invalid regular expression: '{}'", sequence_match_regex);
-    } else if !regex_matcher.unwrap().is_match(record_name) {
-        return None;
+/// The statistics of all sequences along with the version of their schema, so that consumers can detect changes.
+#[derive(Debug, Clone, Serialize)]
+pub struct SummaryReport<'a> {
+    pub version: u32,
+    pub tool_version: &'static str,
+    pub sequences: &'a [SequenceStatistics],
+}
+
+impl<'a> SummaryReport<'a> {
+    pub fn new(sequences: &'a [SequenceStatistics]) -> Self {
+        SummaryReport { version: SUMMARY_SCHEMA_VERSION, tool_version: env!("CARGO_PKG_VERSION"), sequences }
     }
+}
 
-    // Report empty sequences with all statistics set to zero.
-    if record.sequence().len() == 0 {
-        return Some(SequenceStatistics {
-            sequence_name: record_name.to_string(),
-            non_masked_bases: 0,
-            soft_masked_bases: 0,
-            hard_masked_bases: 0,
-            non_masked_ratio: 0.0,
-            soft_masked_ratio: 0.0,
-            hard_masked_ratio: 0.0,
-            gc_content: 0.0,
-            other_iupac_bases: 0,
-            sequence_length: 0,
-            checksum_sha256: "".to_string(),
-        });
+#[derive(Debug, Clone, Serialize)]
+pub struct AssemblyStatistics {
+    pub n50: usize,
+    pub l50: usize,
+    pub total_length: usize,
+    pub num_sequences: usize,
+    pub longest: usize,
+    pub shortest: usize,
+    pub mean_length: f64,
+    /// Median sequence length, i.e., the mean of the two middle lengths if there is an even number of sequences.
+    pub median_length: f64,
+    /// Fraction of the total length in the largest 1, 5, 10, 50, and 100 sequences, as `(number, fraction)` pairs
+    /// (only for numbers up to the number of sequences).
+    pub top_n_fraction: Vec<(usize, f64)>,
+    /// Nx for `x` from 0 to 100 (in percent), as `(x, nx)` pairs: the length of the sequence at which the cumulative
+    /// length (longest first) reaches `x` percent of the total, so that N0 is the longest length, N50 is [Self::n50],
+    /// and N100 is the shortest length. It is not part of the serialized statistics, see `--nx-curve`.
+    #[serde(skip)]
+    pub nx_curve: Vec<(usize, usize)>,
+}
+
+/// Numbers of largest sequences for which the fraction of the total length is reported, see
+/// [AssemblyStatistics::top_n_fraction].
+const TOP_N_SEQUENCES: [usize; 5] = [1, 5, 10, 50, 100];
+
+pub fn compute_assembly_statistics(stats: &[SequenceStatistics]) -> AssemblyStatistics {
+    let mut lengths: Vec<usize> = stats.iter().map(|s| s.sequence_length).collect();
+    lengths.sort_unstable_by(|a, b| b.cmp(a));
+    let total_length: usize = lengths.iter().sum();
+
+    // N50 is the length of the sequence at which the cumulative length (longest first) reaches half the total.
+    let mut n50: usize = 0;
+    let mut l50: usize = 0;
+    let mut cumulative_length: usize = 0;
+    for length in &lengths {
+        cumulative_length += length;
+        l50 += 1;
+        if cumulative_length * 2 >= total_length {
+            n50 = *length;
+            break;
+        }
     }
 
-    let mut sha256_hasher = Sha256::new();
+    let top_n_fraction = TOP_N_SEQUENCES
+        .iter()
+        .filter(|n| **n <= lengths.len())
+        .map(|n| (*n, safe_ratio(lengths[..*n].iter().sum(), total_length)))
+        .collect();
 
-    let mut non_mask_bed_writer = create_bed_writer(output_dir, "non-masked", record_name);
-    let mut soft_mask_bed_writer = create_bed_writer(output_dir, "soft-masked", record_name);
-    let mut hard_mask_bed_writer = create_bed_writer(output_dir, "hard-masked", record_name);
+    // All Nx are computed in a single pass, as the cumulative length only increases with x.
+    let mut nx_curve = Vec::with_capacity(101);
+    let mut index: usize = 0;
+    cumulative_length = lengths.first().copied().unwrap_or(0);
+    for x in 0..=100 {
+        while index + 1 < lengths.len() && cumulative_length * 100 < x * total_length {
+            index += 1;
+            cumulative_length += lengths[index];
+        }
+        nx_curve.push((x, lengths.get(index).copied().unwrap_or(0)));
+    }
 
-    let sequence: &[u8] = record.sequence().as_ref();
+    let middle = lengths.len() / 2;
+    let median_length = match lengths.len() {
+        0 => 0.0,
+        count if count % 2 == 0 => (lengths[middle - 1] + lengths[middle]) as f64 / 2.0,
+        _ => lengths[middle] as f64,
+    };
 
-    let mut index1: usize = 0;
-    let mut gc_counter: usize = 0;
-    let mut other_iupac_bases_counter: usize = 0;
+    AssemblyStatistics {
+        n50,
+        l50,
+        total_length,
+        num_sequences: lengths.len(),
+        longest: lengths.first().copied().unwrap_or(0),
+        shortest: lengths.last().copied().unwrap_or(0),
+        mean_length: safe_ratio(total_length, lengths.len()),
+        median_length,
+        top_n_fraction,
+        nx_curve,
+    }
+}
 
-    let mut non_mask_counter: usize = 0;
-    let mut soft_mask_counter: usize = 0;
-    let mut hard_mask_counter: usize = 0;
+/// Name of the group of sequences whose names do not match the regex of [group_assembly_statistics].
+pub const UNGROUPED_NAME: &str = "ungrouped";
 
-    let mut non_mask_region_start1: Option<usize> = None;
-    let mut soft_mask_region_start1: Option<usize> = None;
-    let mut hard_mask_region_start1: Option<usize> = None;
+/// Computes the [AssemblyStatistics] per group of sequences, where the group of a sequence is the part of its name that
+/// is captured by the single capture group of `group_regex` (which must match the whole name, as with
+/// [StatsOptions::match_regex]). Sequences whose names do not match are grouped under [UNGROUPED_NAME].
+pub fn group_assembly_statistics(
+    stats: &[SequenceStatistics],
+    group_regex: &str,
+) -> Result<BTreeMap<String, AssemblyStatistics>, ProcessError> {
+    let group_matcher = Regex::new(ensure_full_match_regex(group_regex).as_str())
+        .map_err(|_| ProcessError::InvalidRegex(group_regex.to_string()))?;
+    // The implicit group 0 is the whole match, so there must be exactly one more.
+    if group_matcher.captures_len() != 2 {
+        return Err(ProcessError::InvalidRegex(group_regex.to_string()));
+    }
 
-    for base in sequence {
-        index1 += 1;
+    let mut groups: BTreeMap<String, Vec<SequenceStatistics>> = BTreeMap::new();
+    for sequence_stats in stats {
+        let group = group_matcher
+            .captures(&sequence_stats.sequence_name)
+            .and_then(|captures| captures.get(1))
+            .map_or(UNGROUPED_NAME, |group| group.as_str());
+        groups.entry(group.to_string()).or_default().push(sequence_stats.clone());
+    }
+    Ok(groups
+        .into_iter()
+        .map(|(group, group_stats)| (group, compute_assembly_statistics(&group_stats)))
+        .collect())
+}
 
-        let mut non_masking: bool = false;
-        let mut soft_masking: bool = false;
-        let mut hard_masking: bool = false;
+/// Groups the names of sequences with identical [SequenceStatistics::checksum_sha256] by that checksum, keeping only
+/// checksums that are shared by several sequences (in the order of the given statistics). Sequences with an empty
+/// checksum (i.e., empty sequences, or if no SHA256 checksum was computed) are ignored.
+pub fn find_duplicate_sequences(stats: &[SequenceStatistics]) -> BTreeMap<String, Vec<String>> {
+    let mut names_by_checksum: HashMap<&str, Vec<String>> = HashMap::new();
+    for sequence_stats in stats.iter().filter(|sequence_stats| !sequence_stats.checksum_sha256.is_empty()) {
+        names_by_checksum
+            .entry(&sequence_stats.checksum_sha256)
+            .or_default()
+            .push(sequence_stats.sequence_name.clone());
+    }
+    names_by_checksum
+        .into_iter()
+        .filter(|(_, names)| names.len() > 1)
+        .map(|(checksum, names)| (checksum.to_string(), names))
+        .collect()
+}
 
-        sha256_hasher.update([*base]);
-        match *base {
-            b'C' | b'G' => {
-                gc_counter += 1;
-                non_mask_counter += 1;
-                non_masking = true;
-            }
-            b'c' | b'g' => {
-                gc_counter += 1;
-                soft_mask_counter += 1;
-                soft_masking = true;
-            }
-            b'A' | b'T' => {
-                non_mask_counter += 1;
-                non_masking = true;
-            }
-            b'a' | b't' => {
-                soft_mask_counter += 1;
-                soft_masking = true;
-            }
-            b'n' | b'N' => {
-                hard_mask_counter += 1;
-                hard_masking = true;
-            }
-            _ => {
-                if !ignore_iupac {
-                    panic!("Unexpected base: '{}' in sequence '{}'.", *base as char, record_name);
-                } else if !is_unsupported_iupac_code(*base) {
-                    panic!("Unexpected (non-IUPAC) base: '{}' in sequence '{}'.", *base as char, record_name);
-                } else {
-                    other_iupac_bases_counter += 1;
-                }
+/// Counts of `A`, `C`, `G`, and `T` (case-insensitive) per position over sequences of the same length (e.g., a set of
+/// oligos or motifs), see [StatsOptions::position_frequency_matrix]. Sequences are added as they are processed, so that
+/// only the matrix itself is kept in memory.
+#[derive(Debug, Default)]
+pub struct PositionFrequencyMatrix {
+    counts: Option<Vec<[usize; 4]>>,
+}
+
+impl PositionFrequencyMatrix {
+    /// Adds the bases of the sequence at each position. The sequence must have the same length as all sequences added
+    /// before, otherwise nothing is added.
+    pub fn add(&mut self, name: &str, sequence: &[u8]) -> Result<(), ProcessError> {
+        let counts = self.counts.get_or_insert_with(|| vec![[0; 4]; sequence.len()]);
+        if sequence.len() != counts.len() {
+            return Err(ProcessError::UnequalLengths {
+                sequence: name.to_string(),
+                length: sequence.len(),
+                expected_length: counts.len(),
+            });
+        }
+        for (row, base) in counts.iter_mut().zip(sequence) {
+            if let Some(index) = NUCLEOTIDES.iter().position(|nucleotide| *nucleotide == base.to_ascii_uppercase()) {
+                row[index] += 1;
             }
         }
+        Ok(())
+    }
 
-        update_mask_region(&mut non_mask_region_start1, non_masking, non_mask_bed_writer.as_mut(), record_name, index1);
-        update_mask_region(&mut soft_mask_region_start1, soft_masking, soft_mask_bed_writer.as_mut(), record_name, index1);
-        update_mask_region(&mut hard_mask_region_start1, hard_masking, hard_mask_bed_writer.as_mut(), record_name, index1);
+    /// The counts of `A`, `C`, `G`, and `T` at each position (none if no sequence has been added yet).
+    pub fn counts(&self) -> &[[usize; 4]] {
+        self.counts.as_deref().unwrap_or_default()
     }
+}
 
-    // Write the last regions if they were not closed yet (with index of base after last base).
-    update_mask_region(&mut non_mask_region_start1, false, non_mask_bed_writer.as_mut(), record_name, index1 + 1);
-    update_mask_region(&mut soft_mask_region_start1, false, soft_mask_bed_writer.as_mut(), record_name, index1 + 1);
-    update_mask_region(&mut hard_mask_region_start1, false, hard_mask_bed_writer.as_mut(), record_name, index1 + 1);
+/// Name of the statistics that aggregate all sequences, see [total_sequence_statistics].
+pub const TOTAL_SEQUENCE_NAME: &str = "TOTAL";
 
-    assert!(
-        non_mask_counter + soft_mask_counter + hard_mask_counter + other_iupac_bases_counter == sequence.len(),
-        "The sum of masked bases does not match the sequence length ({}) for '{}'. This seems to be a bug.",
-        sequence.len(),
-        record_name
-    );
-    return Some(SequenceStatistics {
-        sequence_name: record_name.to_string(),
-        non_masked_bases: non_mask_counter,
-        soft_masked_bases: soft_mask_counter,
-        hard_masked_bases: hard_mask_counter,
-        non_masked_ratio: non_mask_counter as f64 / sequence.len() as f64,
-        soft_masked_ratio: soft_mask_counter as f64 / sequence.len() as f64,
-        hard_masked_ratio: hard_mask_counter as f64 / sequence.len() as f64,
-        gc_content: gc_counter as f64 / sequence.len() as f64,
-        other_iupac_bases: other_iupac_bases_counter,
-        sequence_length: sequence.len(),
-        checksum_sha256: format!("{:x}", sha256_hasher.finalize()),
-    });
+/// Aggregates the statistics of all sequences into a single record named [TOTAL_SEQUENCE_NAME] (or returns `None` if
+/// there are none), see [merge_sequence_statistics]: counts are summed up and ratios are recomputed against the total.
+/// Its checksums are left empty, as hashing the concatenation of all sequences would require another pass over them.
+pub fn total_sequence_statistics(sequence_statistics: &[SequenceStatistics]) -> Option<SequenceStatistics> {
+    // Gap lengths are collected separately, as merging them one record at a time would take quadratic time.
+    let without_gap_lengths = |stats: &SequenceStatistics| SequenceStatistics {
+        gap_lengths: Vec::new(),
+        soft_mask_region_lengths: stats.soft_mask_region_lengths.as_ref().map(|_| Vec::new()),
+        unexpected_bases: Vec::new(),
+        output_files: Vec::new(),
+        ..stats.clone()
+    };
+    let mut total = sequence_statistics
+        .iter()
+        .map(without_gap_lengths)
+        .reduce(|total, stats| merge_sequence_statistics(&total, &stats))?;
+    total.sequence_name = TOTAL_SEQUENCE_NAME.to_string();
+    total.source_file = "".to_string();
+    total.gap_lengths = sequence_statistics.iter().flat_map(|stats| stats.gap_lengths.iter().copied()).collect();
+    if let Some(lengths) = total.soft_mask_region_lengths.as_mut() {
+        lengths.extend(sequence_statistics.iter().flat_map(|stats| stats.soft_mask_region_lengths.iter().flatten()));
+    }
+    total.checksum_sha256 = "".to_string();
+    total.checksum_md5 = total.checksum_md5.map(|_| "".to_string());
+    total.checksum_blake3 = total.checksum_blake3.map(|_| "".to_string());
+    total.checksum_canonical = total.checksum_canonical.map(|_| "".to_string());
+    Some(total)
 }
 
-fn create_bed_writer(
-    output_dir: Option<&Path>,
-    bed_ending: &str,
-    record_name: &str,
-) -> Option<BedWriter<3, BufWriter<File>>> {
-    output_dir.and_then(|output_dir| {
-        let output_path = output_dir.join(&format!("{}.{}.bed", record_name, bed_ending));
-        Some(
-            bed::io::writer::Builder::default()
-                .build_from_path(output_path.clone())
-                .expect(&format!(
-                    "Could not write to output BED file '{}'.",
-                    output_path.to_str().unwrap()
-                )),
-        )
-    })
+/// Merges the statistics of two records with the same name as if their sequences had been concatenated, except that
+/// runs and gaps do not extend across the boundary between them. Counts are added up and ratios are recomputed; the GC
+/// content is averaged weighted by sequence length (which is exact unless [StatsOptions::iupac_aware] is set).
+/// Checksums cannot be merged, so they are left empty.
+pub fn merge_sequence_statistics(first: &SequenceStatistics, second: &SequenceStatistics) -> SequenceStatistics {
+    let sequence_length = first.sequence_length + second.sequence_length;
+    let (non_masked_bases, soft_masked_bases, hard_masked_bases) = (
+        first.non_masked_bases + second.non_masked_bases,
+        first.soft_masked_bases + second.soft_masked_bases,
+        first.hard_masked_bases + second.hard_masked_bases,
+    );
+    let (a_count, c_count, g_count, t_count) = (
+        first.a_count + second.a_count,
+        first.c_count + second.c_count,
+        first.g_count + second.g_count,
+        first.t_count + second.t_count,
+    );
+    // Positions refer to the alignment columns if there are alignment gaps.
+    let first_length = first.alignment_length.unwrap_or(first.sequence_length);
+    let longest_homopolymer_stats = if second.longest_homopolymer > first.longest_homopolymer { second } else { first };
+
+    SequenceStatistics {
+        sequence_name: first.sequence_name.clone(),
+        description: first.description.clone(),
+        source_file: first.source_file.clone(),
+        non_masked_bases,
+        soft_masked_bases,
+        hard_masked_bases,
+        non_masked_ratio: safe_ratio(non_masked_bases, sequence_length),
+        soft_masked_ratio: safe_ratio(soft_masked_bases, sequence_length),
+        hard_masked_ratio: safe_ratio(hard_masked_bases, sequence_length),
+        non_masked_region_count: first.non_masked_region_count + second.non_masked_region_count,
+        soft_masked_region_count: first.soft_masked_region_count + second.soft_masked_region_count,
+        hard_masked_region_count: first.hard_masked_region_count + second.hard_masked_region_count,
+        soft_mask_region_mean_length: safe_ratio(
+            soft_masked_bases,
+            first.soft_masked_region_count + second.soft_masked_region_count,
+        ),
+        soft_mask_region_max_length: first.soft_mask_region_max_length.max(second.soft_mask_region_max_length),
+        soft_mask_region_lengths: first.soft_mask_region_lengths.as_ref().map(|first_lengths| {
+            [first_lengths.as_slice(), second.soft_mask_region_lengths.as_deref().unwrap_or_default()].concat()
+        }),
+        gc_content: if sequence_length > 0 {
+            (first.gc_content * first.sequence_length as f64 + second.gc_content * second.sequence_length as f64)
+                / sequence_length as f64
+        } else {
+            0.0
+        },
+        gc_content_no_n: safe_ratio(g_count + c_count, a_count + c_count + g_count + t_count),
+        gc_content_masked: weighted_average(
+            (first.gc_content_masked, first.soft_masked_bases + first.hard_masked_bases),
+            (second.gc_content_masked, second.soft_masked_bases + second.hard_masked_bases),
+        ),
+        gc_content_unmasked: weighted_average(
+            (first.gc_content_unmasked, first.non_masked_bases),
+            (second.gc_content_unmasked, second.non_masked_bases),
+        ),
+        gc_content_weighted: weighted_average(
+            (first.gc_content_weighted, first.sequence_length),
+            (second.gc_content_weighted, second.sequence_length),
+        ),
+        at_content: safe_ratio(a_count + t_count, sequence_length),
+        gc_skew: skew(g_count, c_count),
+        at_skew: skew(a_count, t_count),
+        // The cumulative GC skew of the second record depends on the unknown skew windows of the first one.
+        gc_skew_min_position: None,
+        gc_skew_max_position: None,
+        iupac_ambiguous_bases: first.iupac_ambiguous_bases + second.iupac_ambiguous_bases,
+        other_bases: first.other_bases + second.other_bases,
+        a_count,
+        c_count,
+        g_count,
+        t_count,
+        n_count: first.n_count + second.n_count,
+        shannon_entropy: shannon_entropy(&[a_count, c_count, g_count, t_count]),
+        longest_homopolymer: longest_homopolymer_stats.longest_homopolymer,
+        longest_homopolymer_base: longest_homopolymer_stats.longest_homopolymer_base,
+        num_gaps: first.num_gaps + second.num_gaps,
+        gap_lengths: [first.gap_lengths.as_slice(), second.gap_lengths.as_slice()].concat(),
+        longest_ungapped_run: first.longest_ungapped_run.max(second.longest_ungapped_run),
+        dinucleotide_counts: first.dinucleotide_counts.as_ref().map(|first_counts| {
+            let mut counts = first_counts.clone();
+            for (dinucleotide, count) in second.dinucleotide_counts.iter().flatten() {
+                *counts.entry(dinucleotide.clone()).or_insert(0) += count;
+            }
+            counts
+        }),
+        cpg_count: first.cpg_count.map(|count| count + second.cpg_count.unwrap_or(0)),
+        cpg_observed_expected_ratio: first.cpg_count.map(|count| {
+            cpg_observed_expected_ratio(count + second.cpg_count.unwrap_or(0), a_count, c_count, g_count, t_count)
+        }),
+        tm_celsius: first
+            .tm_celsius
+            .and(second.tm_celsius)
+            .and_then(|_| melting_temperature(a_count, c_count, g_count, t_count, sequence_length)),
+        sequence_length,
+        gap_dash_bases: first.gap_dash_bases.map(|count| count + second.gap_dash_bases.unwrap_or(0)),
+        alignment_length: first.alignment_length.map(|length| length + second.alignment_length.unwrap_or(0)),
+        checksum_sha256: "".to_string(),
+        checksum_md5: first.checksum_md5.as_ref().map(|_| "".to_string()),
+        checksum_blake3: first.checksum_blake3.as_ref().map(|_| "".to_string()),
+        checksum_canonical: first.checksum_canonical.as_ref().map(|_| "".to_string()),
+        processing_ms: first.processing_ms.map(|first_ms| first_ms + second.processing_ms.unwrap_or(0.0)),
+        unexpected_bases: first
+            .unexpected_bases
+            .iter()
+            .copied()
+            .chain(second.unexpected_bases.iter().map(|(position, base)| (first_length + position, *base)))
+            .take(MAX_REPORTED_UNEXPECTED_BASES)
+            .collect(),
+        bed_writing_time: first.bed_writing_time + second.bed_writing_time,
+        output_files: [first.output_files.as_slice(), second.output_files.as_slice()].concat(),
+    }
 }
 
-fn write_bed_record<X: std::io::Write>(
-    writer: &mut BedWriter<3, X>,
-    sequence_name: &str,
-    start1: usize,
-    end1: usize,
-) -> Result<(), Box<dyn Error>> {
-    let record = RecordBuf::<3>::builder()
-        .set_reference_sequence_name(sequence_name)
-        .set_feature_start(Position::try_from(start1)?)
-        .set_feature_end(Position::try_from(end1 - 1)?) // Method is called *after* the end of region is reached, so we need to subtract 1.
-        .build();
-    writer.write_feature_record(&record)?;
-    Ok(())
+/// Rounds all floating-point fields (i.e., ratios, contents, skews, entropy, and melting temperature) to the given
+/// number of decimal places, e.g., for a less noisy output.
+pub fn round_statistics(stats: &mut SequenceStatistics, digits: u32) {
+    let factor = 10f64.powi(digits as i32);
+    let round = |value: &mut f64| *value = (*value * factor).round() / factor;
+    for value in [
+        &mut stats.non_masked_ratio,
+        &mut stats.soft_masked_ratio,
+        &mut stats.hard_masked_ratio,
+        &mut stats.gc_content,
+        &mut stats.gc_content_no_n,
+        &mut stats.gc_content_masked,
+        &mut stats.gc_content_unmasked,
+        &mut stats.gc_content_weighted,
+        &mut stats.soft_mask_region_mean_length,
+        &mut stats.at_content,
+        &mut stats.gc_skew,
+        &mut stats.at_skew,
+        &mut stats.shannon_entropy,
+    ] {
+        round(value);
+    }
+    stats.cpg_observed_expected_ratio.as_mut().map(round);
+    stats.tm_celsius.as_mut().map(round);
+    stats.processing_ms.as_mut().map(round);
 }
 
-fn update_mask_region<X: std::io::Write>(
-    region_start: &mut Option<usize>,
-    masking: bool,
-    writer_opt: Option<&mut BedWriter<3, X>>,
-    record_name: &str,
-    index1: usize,
-) {
-    if let Some(writer) = writer_opt {
-        if masking {
-            if region_start.is_none() {
-                *region_start = Some(index1);
+/// Combines the statistics of several runs (e.g., of jobs that processed parts of a genome) into one list, in the order
+/// in which the sequences first appear. Statistics of sequences with the same name in several parts (e.g., processed in
+/// chunks) are merged in order, see [merge_sequence_statistics]. Note that the checksums of merged sequences are left
+/// empty, as they cannot be computed from the checksums of the parts.
+pub fn merge_statistics(parts: Vec<Vec<SequenceStatistics>>) -> Vec<SequenceStatistics> {
+    let mut merged: Vec<SequenceStatistics> = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+    for stats in parts.into_iter().flatten() {
+        match indices.get(&stats.sequence_name) {
+            Some(&index) => merged[index] = merge_sequence_statistics(&merged[index], &stats),
+            None => {
+                indices.insert(stats.sequence_name.clone(), merged.len());
+                merged.push(stats);
             }
-        } else if let Some(start1) = *region_start {
-            let _ = write_bed_record(writer, record_name, start1, index1);
-            *region_start = None;
         }
     }
+    merged
 }
 
-fn is_unsupported_iupac_code(base: u8) -> bool {
-    matches!(base, b'b' | b'B' | b'd' | b'D' | b'h' | b'H' | b'k' | b'K' | b'm' | b'M' | b'r' | b'R' | b's' | b'S' | b'v' | b'V' | b'w' | b'W' | b'y' | b'Y')
+/// Options that control how the statistics of a sequence are computed.
+#[derive(Debug, Clone, Default)]
+pub struct StatsOptions {
+    /// Directory for the per-sequence output files (e.g., BED files). No files are written if not set.
+    pub output_dir: Option<PathBuf>,
+    /// Directory for the BED files of each sequence instead of [Self::output_dir] (which still needs to be set for
+    /// writing them).
+    pub bed_dir: Option<PathBuf>,
+    /// Only process sequences whose name fully matches this regular expression (all sequences if not set).
+    pub match_regex: Option<String>,
+    /// Count ambiguous IUPAC codes (and any other unexpected characters) instead of failing on them.
+    pub ignore_iupac: bool,
+    /// Count ambiguous IUPAC codes fractionally towards the GC content, see [gc_contribution].
+    pub iupac_aware: bool,
+    pub checksum_kind: ChecksumKind,
+    /// Convert all bases to upper case before feeding them into the checksum(s).
+    pub checksum_uppercase: bool,
+    /// Only compute the length and checksum(s) of each sequence, leaving all other statistics at zero and writing no
+    /// per-sequence files.
+    pub checksum_only: bool,
+    /// Compute the strand-independent [SequenceStatistics::checksum_canonical].
+    pub canonical_checksum: bool,
+    /// Do not compute any checksum, leaving [SequenceStatistics::checksum_sha256] empty.
+    pub no_checksum: bool,
+    /// Fail on the first lowercase (i.e., soft-masked) base, see [ProcessError::UnexpectedSoftMask].
+    pub strict: bool,
+    /// Do not write the per-sequence BED files into the output directory.
+    pub no_bed_output: bool,
+    /// Window size for writing the GC content per window into a bedGraph file, see [write_gc_bedgraph].
+    pub gc_window: Option<usize>,
+    /// Number of bins for writing the GC content profile of each sequence, see [write_gc_profile].
+    pub gc_bins: Option<usize>,
+    /// Window size for writing the masked fraction per window into a bedGraph file, see [write_mask_bedgraph].
+    pub mask_window: Option<usize>,
+    /// Window size for writing the cumulative GC skew into a bedGraph file, see [write_gc_skew_bedgraph].
+    pub gc_skew_window: Option<usize>,
+    /// Write the masking of each base into a binary file, see [write_mask_track].
+    pub mask_track: bool,
+    pub on_duplicate: DuplicatePolicy,
+    /// Collect the lengths of all soft-masked regions, see [SequenceStatistics::soft_mask_region_lengths].
+    pub soft_mask_region_lengths: bool,
+    /// Count dinucleotides, see [SequenceStatistics::dinucleotide_counts].
+    pub dinucleotides: bool,
+    /// Estimate the melting temperature, see [SequenceStatistics::tm_celsius].
+    pub tm: bool,
+    /// Matrix to which the bases at each position of every sequence (or region) are added once its statistics have been
+    /// computed, shared between all sequences.
+    pub position_frequency_matrix: Option<Arc<Mutex<PositionFrequencyMatrix>>>,
+    /// Measure the processing time of each record, see [SequenceStatistics::processing_ms].
+    pub record_timings: bool,
+    /// Size of the k-mers to count into a TSV file per sequence, see [write_kmer_counts].
+    pub kmer_size: Option<usize>,
+    pub alphabet: Alphabet,
+    pub bed_columns: BedColumns,
+    /// Write the BED files gzip-compressed (as `.bed.gz`).
+    pub compress_bed: bool,
+    /// Additionally write the soft- and hard-masked regions merged into a single `masked` BED file.
+    pub merge_mask_bed: bool,
+    /// Count lowercase `acgt` as non-masked (like uppercase ones) instead of soft-masked.
+    pub no_soft_mask: bool,
+    /// Weight of soft-masked bases for [SequenceStatistics::gc_content_weighted] (`1.0` if not set), clamped to
+    /// `[0, 1]`.
+    pub softmask_weight: Option<f64>,
+    /// Count `n` as soft-masked (and only `N` as hard-masked, i.e., as gap) instead of both as hard-masked.
+    pub split_n_case: bool,
+    /// Count `-` as alignment gap (see [SequenceStatistics::gap_dash_bases]) instead of as an unexpected base.
+    pub alignment_mode: bool,
+    /// The characters that count as hard-masked, i.e., as gap (`N` and `n` if not set). They are counted as
+    /// [SequenceStatistics::n_count], and must not contain any of `ACGT` (in either case).
+    pub mask_chars: Option<String>,
+    /// Ignore sequences whose name fully matches this regular expression, even if they match the include pattern.
+    pub exclude_regex: Option<String>,
+    /// Match [Self::match_regex] and [Self::exclude_regex] case-insensitively (e.g., `chr1` and `Chr1`).
+    pub case_insensitive_names: bool,
+    /// Prefix for the names of the per-sequence output files, see [prefixed_file_name].
+    pub file_prefix: String,
+    /// Ignore sequences shorter than this length.
+    pub min_length: Option<usize>,
+    /// Ignore sequences longer than this length.
+    pub max_length: Option<usize>,
+    /// Collect the positions of bases other than `ACGTN`, see [SequenceStatistics::unexpected_bases].
+    pub report_unexpected: bool,
+    /// Only compute the statistics of the bases within these regions (instead of the whole sequence), skipping
+    /// sequences without any region. No per-sequence files are written then.
+    pub regions: Option<Arc<SequenceRegions>>,
+    /// Report the statistics of each region separately (named `<sequence>:<start>-<end>`, with 1-based, inclusive
+    /// coordinates) instead of those of all regions of a sequence, see [Self::regions].
+    pub per_region: bool,
+    /// Statistics of a previous run per sequence name, which are reused for sequences with the same length and
+    /// checksum(s) instead of processing them again, see [reuse_previous_statistics].
+    pub previous_statistics: Option<Arc<HashMap<String, SequenceStatistics>>>,
 }
 
-fn ensure_full_match_regex(regex: &str) -> String {
-    let start_ok = regex.starts_with('^');
-    let end_ok = regex.ends_with('$');
-    if start_ok && end_ok {
-        regex.to_string()
-    } else if start_ok && !end_ok {
-        format!("{}$", regex)
-    } else if !start_ok && end_ok {
-        format!("^{}", regex)
-    } else {
-        format!("^{}$", regex)
+/// Intervals (0-based, half-open, and sorted by start) per sequence name, see [StatsOptions::regions].
+pub type SequenceRegions = HashMap<String, Vec<(usize, usize)>>;
+
+/// Reads the intervals from a BED file (with at least three columns).
+pub fn read_regions<R: BufRead>(reader: R) -> Result<SequenceRegions, std::io::Error> {
+    let mut reader = bed::io::Reader::<3, _>::new(reader);
+    let mut record = bed::Record::default();
+    let mut regions = SequenceRegions::new();
+    while reader.read_record(&mut record)? > 0 {
+        // Positions are 1-based, so the start is converted back and the (inclusive) end is the exclusive 0-based end.
+        let start = record.feature_start()?.get() - 1;
+        let end = record
+            .feature_end()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "Missing end of BED region."))??
+            .get();
+        regions.entry(record.reference_sequence_name().to_string()).or_default().push((start, end));
     }
+    for intervals in regions.values_mut() {
+        intervals.sort_unstable();
+    }
+    Ok(regions)
 }
 
-#[cfg(test)]
-mod tests {
+/// Reads the statistics of a previous summary, which may be a JSON summary (with or without the schema version, see
+/// [SummaryReport]) or hold one JSON object per line (JSON Lines).
+pub fn read_summary<R: BufRead>(reader: R) -> Result<Vec<SequenceStatistics>, std::io::Error> {
+    let mut sequence_statistics = Vec::new();
+    for value in serde_json::Deserializer::from_reader(reader).into_iter::<serde_json::Value>() {
+        match value? {
+            serde_json::Value::Object(mut report) if report.contains_key("sequences") => {
+                sequence_statistics.extend(serde_json::from_value::<Vec<_>>(report["sequences"].take())?)
+            }
+            array @ serde_json::Value::Array(_) => sequence_statistics.extend(serde_json::from_value::<Vec<_>>(array)?),
+            stats => sequence_statistics.push(serde_json::from_value(stats)?),
+        }
+    }
+    Ok(sequence_statistics)
+}
 
-    use super::*;
+/// Returns the previous statistics of the sequence (see [StatsOptions::previous_statistics]) if it has the same length
+/// and, where both the previous and the current options provide one, the same checksums. Otherwise, the sequence is
+/// considered new or changed and none are returned.
+fn reuse_previous_statistics(name: &str, sequence: &[u8], options: &StatsOptions) -> Option<SequenceStatistics> {
+    let previous = options.previous_statistics.as_ref()?.get(name)?;
+    if previous.alignment_length.unwrap_or(previous.sequence_length) != sequence.len() {
+        return None;
+    }
+    let previous_checksums = [
+        Some(&previous.checksum_sha256),
+        previous.checksum_md5.as_ref(),
+        previous.checksum_blake3.as_ref(),
+        previous.checksum_canonical.as_ref(),
+    ];
+    if previous_checksums.iter().flatten().all(|checksum| checksum.is_empty()) {
+        return Some(previous.clone());
+    }
+    let checksums = compute_checksums(sequence, options);
+    let current_checksums = [checksums.sha256, checksums.md5, checksums.blake3, checksums.canonical];
+    let unchanged = previous_checksums.into_iter().zip(current_checksums).all(|checksums| match checksums {
+        (Some(previous), Some(current)) if !previous.is_empty() && !current.is_empty() => *previous == current,
+        _ => true,
+    });
+    unchanged.then(|| previous.clone())
+}
 
-    #[test]
-    fn ensure_full_match_regex_ok() {
-        assert_eq!(ensure_full_match_regex(".*"), "^.*$");
-        assert_eq!(ensure_full_match_regex("^.*"), "^.*$");
-        assert_eq!(ensure_full_match_regex(".*$"), "^.*$");
-        assert_eq!(ensure_full_match_regex("^.*$"), "^.*$");
-        assert_eq!(ensure_full_match_regex("abc"), "^abc$");
-        assert_eq!(ensure_full_match_regex("^abc"), "^abc$");
-        assert_eq!(ensure_full_match_regex("abc$"), "^abc$");
-        assert_eq!(ensure_full_match_regex("^abc$"), "^abc$");
+/// Returns a function that processes a record, given the number of times its name has been seen so far (including
+/// the record itself, i.e., `1` for the first record with that name), and returns its statistics (none if it is
+/// skipped, and one per region with [StatsOptions::per_region]). With [DuplicatePolicy::Merge], records with the
+/// same name must not be processed concurrently, as their regions are appended to the same output files.
+pub fn process_fasta(
+    combined_bed_output: Option<&Mutex<dyn Write + Send>>,
+    options: &StatsOptions,
+) -> impl Fn(&FastaRecord, usize) -> Result<Vec<SequenceStatistics>, ProcessError> {
+    move |record, occurrence| {
+        let processing_start = Instant::now();
+        let stats = match &options.regions {
+            Some(regions) => process_fasta_record_regions(record, occurrence, regions, options)?,
+            None => process_fasta_record(record, occurrence, combined_bed_output, options)?.into_iter().collect(),
+        };
+        // All statistics of a record (e.g., per region) get the processing time of the whole record.
+        let processing_ms = options.record_timings.then(|| processing_start.elapsed().as_secs_f64() * 1000.0);
+        let description = record.description().map(|description| description.to_str_lossy().into_owned());
+        Ok(stats
+            .into_iter()
+            .map(|stats| SequenceStatistics { description: description.clone(), processing_ms, ..stats })
+            .collect())
     }
+}
 
-    #[test]
-    fn process_fasta_record_ok() -> Result<(), Box<dyn Error>> {
-        let record = FastaRecord::new(
-            noodles_fasta::record::Definition::new("test_sequence", None),
-            noodles_fasta::record::Sequence::from(
-                b"CTGTGCTGGCATAGTGGTCTCACCTCCGGCAGtatcaccaccactgggcacaagcttctccagcacagcaNNnNnactgtgtcttatttctccttgtactcccagtgttcacaccatgctgcactcacagaagactcttcgttgatattt".to_vec()),
-        );
+/// Compiles the regular expression to fully match sequence names (case-insensitively with
+/// [StatsOptions::case_insensitive_names]).
+fn name_matcher(regex: &str, options: &StatsOptions) -> Result<Regex, ProcessError> {
+    RegexBuilder::new(ensure_full_match_regex(regex).as_str())
+        .case_insensitive(options.case_insensitive_names)
+        .build()
+        .map_err(|_| ProcessError::InvalidRegex(regex.to_string()))
+}
 
-        let tmpdir = tempfile::tempdir()?;
-        let stats = process_fasta_record(&record, Some(&tmpdir.path()), ".*", false);
-        assert!(stats.is_some());
-        let stats = stats.unwrap();
+/// Checks whether the record is selected by the name and length filters of the options.
+fn is_record_selected(record_name: &str, length: usize, options: &StatsOptions) -> Result<bool, ProcessError> {
+    // Ignore records that do not match the regex
+    if let Some(match_regex) = &options.match_regex {
+        let regex_matcher = name_matcher(match_regex, options)?;
+        if !regex_matcher.is_match(record_name) {
+            warn!("Skipping record '{}': its name does not match '{}'.", record_name, match_regex);
+            return Ok(false);
+        }
+    }
+    if let Some(exclude_regex) = &options.exclude_regex {
+        let exclude_matcher = name_matcher(exclude_regex, options)?;
+        if exclude_matcher.is_match(record_name) {
+            warn!("Skipping record '{}': its name matches the exclude regex '{}'.", record_name, exclude_regex);
+            return Ok(false);
+        }
+    }
 
-        // Check the output BED files
-        let non_masked_bed_path = tmpdir.path().join("test_sequence.non-masked.bed");
-        let non_masked_bed = std::fs::read_to_string(non_masked_bed_path)?;
-        assert_eq!(non_masked_bed, "test_sequence\t0\t32\n");
-        
-        let soft_masked_bed_path = tmpdir.path().join("test_sequence.soft-masked.bed");
-        let soft_masked_bed = std::fs::read_to_string(soft_masked_bed_path)?;
-        assert_eq!(soft_masked_bed, "test_sequence\t32\t70\ntest_sequence\t75\t150\n");
+    // Ignore records outside the length range
+    if options.min_length.is_some_and(|min| length < min) || options.max_length.is_some_and(|max| length > max) {
+        warn!("Skipping record '{}': its length ({} bp) is outside the allowed range.", record_name, length);
+        return Ok(false);
+    }
+    Ok(true)
+}
 
-        let hard_masked_bed_path = tmpdir.path().join("test_sequence.hard-masked.bed");
-        let hard_masked_bed = std::fs::read_to_string(hard_masked_bed_path)?;
-        assert_eq!(hard_masked_bed, "test_sequence\t70\t75\n");
+/// Computes the statistics of the bases of the record within its regions, see [StatsOptions::regions]. Unless
+/// [StatsOptions::per_region] is set, overlapping regions are merged and the statistics of all regions are merged into
+/// one (see [merge_sequence_statistics]), so that its checksums are left empty if there are several regions.
+fn process_fasta_record_regions(
+    record: &FastaRecord,
+    occurrence: usize,
+    regions: &SequenceRegions,
+    options: &StatsOptions,
+) -> Result<Vec<SequenceStatistics>, ProcessError> {
+    let record_name = record_name(record)?;
+    let sequence: &[u8] = record.sequence().as_ref();
+    if !is_record_selected(record_name, sequence.len(), options)? {
+        return Ok(Vec::new());
+    }
+    if occurrence > 1 && options.on_duplicate == DuplicatePolicy::Error {
+        return Err(ProcessError::DuplicateName(record_name.to_string()));
+    }
 
-        assert_eq!(stats.sequence_name, "test_sequence");
-        assert_eq!(stats.non_masked_bases, 32);
-        assert_eq!(stats.non_masked_ratio, 32.0 / 150.0);
+    // Regions are clipped to the sequence, as BED files may refer to another version of it.
+    let intervals: Vec<(usize, usize)> = regions
+        .get(record_name)
+        .into_iter()
+        .flatten()
+        .map(|(start, end)| (*start, (*end).min(sequence.len())))
+        .filter(|(start, end)| start < end)
+        .collect();
+    if intervals.is_empty() {
+        warn!("Skipping record '{}': it does not overlap any region.", record_name);
+        return Ok(Vec::new());
+    }
+    info!("Processing {} region(s) of record '{}'.", intervals.len(), record_name);
 
-        assert_eq!(stats.soft_masked_bases, 113);
-        assert_eq!(stats.soft_masked_ratio, 113.0 / 150.0);
+    if options.per_region {
+        return intervals
+            .iter()
+            .map(|(start, end)| {
+                let region_name = format!("{}:{}-{}", record_name, start + 1, end);
+                compute_statistics(&region_name, &sequence[*start..*end], options)
+            })
+            .collect();
+    }
+    let region_statistics = merge_overlapping_intervals(&intervals)
+        .iter()
+        .map(|(start, end)| compute_statistics(record_name, &sequence[*start..*end], options))
+        .collect::<Result<Vec<_>, _>>()?;
+    let merged_statistics = region_statistics.into_iter().reduce(|total, stats| merge_sequence_statistics(&total, &stats));
+    Ok(merged_statistics.into_iter().collect())
+}
 
-        assert_eq!(stats.hard_masked_bases, 5);
-        assert_eq!(stats.hard_masked_ratio, 5.0 / 150.0);
-        
-        assert_eq!(stats.gc_content, 73.0 / 150.0);
-        assert_eq!(stats.other_iupac_bases, 0);
-        assert_eq!(stats.sequence_length, 150);
-        assert_eq!(stats.checksum_sha256, "39d1aba0a51cb46ce7cef81ac808bf46e3594b0ccabcd77fabf19c2a395651fa");
-        Ok(())
+/// Merges overlapping (and adjacent) intervals, which must be sorted by start.
+fn merge_overlapping_intervals(intervals: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some((_, last_end)) if *start <= *last_end => *last_end = (*last_end).max(*end),
+            _ => merged.push((*start, *end)),
+        }
     }
-    
+    merged
+}
 
-    #[test]
-    fn process_fasta_record_non_iupac_codes_ok() -> Result<(), Box<dyn Error>> {
-        let record = FastaRecord::new(
-            noodles_fasta::record::Definition::new("iupac_test_sequence", None),
-            noodles_fasta::record::Sequence::from(b"mMrvy".to_vec()),
-        );
+fn process_fasta_record(
+    record: &FastaRecord,
+    occurrence: usize,
+    combined_bed_output: Option<&Mutex<dyn Write + Send>>,
+    options: &StatsOptions,
+) -> Result<Option<SequenceStatistics>, ProcessError> {
+    let record_name = record_name(record)?;
+    let output_dir = options.output_dir.as_deref();
+    let length = record.sequence().len();
+    if !is_record_selected(record_name, length, options)? {
+        return Ok(None);
+    }
 
-        let tmpdir = tempfile::tempdir()?;
-        let stats = process_fasta_record(&record, Some(&tmpdir.path()), ".*", true);
-        assert!(stats.is_some());
-        let stats = stats.unwrap();
+    let mut file_stem = prefixed_file_name(&options.file_prefix, &sanitize_filename(record_name));
+    let mut append_output = false;
+    if occurrence > 1 {
+        match options.on_duplicate {
+            DuplicatePolicy::Error => return Err(ProcessError::DuplicateName(record_name.to_string())),
+            DuplicatePolicy::Rename => file_stem = format!("{}.{}", file_stem, occurrence),
+            DuplicatePolicy::Merge => append_output = true,
+        }
+    }
+    if let Some(previous) = reuse_previous_statistics(record_name, record.sequence().as_ref(), options) {
+        info!("Reusing the previous statistics of record '{}' ({} bp).", record_name, length);
+        return Ok(Some(previous));
+    }
+    info!("Processing record '{}' ({} bp).", record_name, length);
 
-        // No BED files should have been created.
-        let non_masked_bed_path = tmpdir.path().join("test_sequence.non-masked.bed");
-        assert_eq!(non_masked_bed_path.exists(), false);       
-        let soft_masked_bed_path = tmpdir.path().join("test_sequence.soft-masked.bed");
-        assert_eq!(soft_masked_bed_path.exists(), false);
-        let hard_masked_bed_path = tmpdir.path().join("test_sequence.hard-masked.bed");
-        assert_eq!(hard_masked_bed_path.exists(), false);
+    // Empty sequences have no regions, so no output files are written for them (nor if only checksums are computed).
+    let sequence: &[u8] = record.sequence().as_ref();
+    if sequence.is_empty() || options.checksum_only {
+        return compute_statistics(record_name, sequence, options).map(Some);
+    }
 
-        assert_eq!(stats.sequence_name, "iupac_test_sequence");
-        assert_eq!(stats.non_masked_bases, 0);
-        assert_eq!(stats.non_masked_ratio, 0.0);
+    let mut output_files: Vec<(PathBuf, &'static str)> = Vec::new();
+    if let (Some(output_dir), Some(gc_window)) = (output_dir, options.gc_window) {
+        let output_path = output_dir.join(format!("{}.gc.bedgraph", file_stem));
+        open_output_file(&output_path, append_output)
+            .map(BufWriter::new)
+            .and_then(|mut writer| write_gc_bedgraph(&mut writer, record_name, sequence, gc_window, options.alphabet))
+            .map_err(|error| ProcessError::WriteError(format!("'{}': {}", output_path.display(), error)))?;
+        output_files.push((output_path, "bedgraph"));
+    }
 
-        assert_eq!(stats.soft_masked_bases, 0);
-        assert_eq!(stats.soft_masked_ratio, 0.0);
+    if let (Some(output_dir), Some(gc_bins)) = (output_dir, options.gc_bins) {
+        let output_path = output_dir.join(format!("{}.gc_profile.tsv", file_stem));
+        open_output_file(&output_path, append_output)
+            .map(BufWriter::new)
+            .and_then(|mut writer| write_gc_profile(&mut writer, sequence, gc_bins, options.alphabet))
+            .map_err(|error| ProcessError::WriteError(format!("'{}': {}", output_path.display(), error)))?;
+        output_files.push((output_path, "gc_profile"));
+    }
 
-        assert_eq!(stats.hard_masked_bases, 0);
-        assert_eq!(stats.hard_masked_ratio, 0.0);
-        
-        assert_eq!(stats.gc_content, 0.0);
-        assert_eq!(stats.other_iupac_bases, 5);
-        assert_eq!(stats.sequence_length, 5);
-        assert_eq!(stats.checksum_sha256, "fed4eff301b269d726775e3a29cdc4b015b3b5d9e2d771ecf9964ccac590d30f");
-        Ok(())
+    if let (Some(output_dir), Some(kmer_size)) = (output_dir, options.kmer_size) {
+        let output_path = output_dir.join(format!("{}.kmers.tsv", file_stem));
+        open_output_file(&output_path, append_output)
+            .map(BufWriter::new)
+            .and_then(|mut writer| write_kmer_counts(&mut writer, sequence, kmer_size))
+            .map_err(|error| ProcessError::WriteError(format!("'{}': {}", output_path.display(), error)))?;
+        output_files.push((output_path, "kmers"));
     }
 
+    let mask_bedgraph = match (output_dir, options.mask_window) {
+        (Some(output_dir), Some(_)) => {
+            let output_path = output_dir.join(format!("{}.mask.bedgraph", file_stem));
+            let file = open_output_file(&output_path, append_output)
+                .map_err(|error| ProcessError::WriteError(format!("'{}': {}", output_path.display(), error)))?;
+            output_files.push((output_path, "bedgraph"));
+            Some(BufWriter::new(file))
+        }
+        _ => None,
+    };
+    let gc_skew_bedgraph = match (output_dir, options.gc_skew_window) {
+        (Some(output_dir), Some(_)) => {
+            let output_path = output_dir.join(format!("{}.gcskew.bedgraph", file_stem));
+            let file = open_output_file(&output_path, append_output)
+                .map_err(|error| ProcessError::WriteError(format!("'{}': {}", output_path.display(), error)))?;
+            output_files.push((output_path, "bedgraph"));
+            Some(BufWriter::new(file))
+        }
+        _ => None,
+    };
+    let mask_track = match output_dir {
+        Some(output_dir) if options.mask_track => {
+            let output_path = output_dir.join(format!("{}.mask.bin", file_stem));
+            let file = File::create(&output_path)
+                .map_err(|error| ProcessError::WriteError(format!("'{}': {}", output_path.display(), error)))?;
+            output_files.push((output_path, "mask_track"));
+            Some(BufWriter::new(file))
+        }
+        _ => None,
+    };
+
+    let bed_output_dir = output_dir
+        .map(|output_dir| options.bed_dir.as_deref().unwrap_or(output_dir))
+        .filter(|_| !options.no_bed_output);
+    let (combined, merged) = (combined_bed_output.is_some(), options.merge_mask_bed);
+    if let Some(bed_output_dir) = bed_output_dir {
+        let extension = if options.compress_bed { "bed.gz" } else { "bed" };
+        let bed_endings = ["non-masked", "soft-masked", "hard-masked"].into_iter().chain(merged.then_some("masked"));
+        output_files.extend(
+            bed_endings.map(|bed_ending| (bed_file_path(bed_output_dir, &file_stem, bed_ending, extension), "bed")),
+        );
+    }
+    let stats = if options.compress_bed {
+        let compress = |file| GzEncoder::new(file, Compression::default());
+        let writers =
+            MaskRegionWriters::create(bed_output_dir, &file_stem, "bed.gz", append_output, combined, merged, compress)
+                .map_err(|error| ProcessError::WriteError(error.to_string()))?;
+        let writers = MaskRegionWriters { mask_bedgraph, gc_skew_bedgraph, mask_track, ..writers };
+        let finish = |encoder: GzEncoder<File>| encoder.finish().map(drop);
+        compute_and_write_regions(record_name, sequence, options, writers, finish, combined_bed_output)?
+    } else {
+        let uncompressed = |file| file;
+        let writers =
+            MaskRegionWriters::create(bed_output_dir, &file_stem, "bed", append_output, combined, merged, uncompressed)
+                .map_err(|error| ProcessError::WriteError(error.to_string()))?;
+        let writers = MaskRegionWriters { mask_bedgraph, gc_skew_bedgraph, mask_track, ..writers };
+        let finish = |_: File| Ok(());
+        compute_and_write_regions(record_name, sequence, options, writers, finish, combined_bed_output)?
+    };
+    Ok(Some(SequenceStatistics { output_files, ..stats }))
+}
+
+/// Computes the statistics of a sequence and writes its masking regions, appending them to the combined BED output
+/// (if any) at once.
+fn compute_and_write_regions<W: Write>(
+    record_name: &str,
+    sequence: &[u8],
+    options: &StatsOptions,
+    mut writers: MaskRegionWriters<W>,
+    finish_file: impl Fn(W) -> Result<(), std::io::Error>,
+    combined_bed_output: Option<&Mutex<dyn Write + Send>>,
+) -> Result<SequenceStatistics, ProcessError> {
+    let mut stats = compute_sequence_statistics(record_name, sequence, options, &mut writers)?;
+
+    // Closing the per-sequence files flushes (and possibly compresses) their remaining content.
+    let bed_writing_start = Instant::now();
+    let combined = writers.combined.take();
+    writers.close(finish_file)?;
+    if let (Some(output), Some(writer)) = (combined_bed_output, combined) {
+        output
+            .lock()
+            .unwrap()
+            .write_all(&writer.into_inner())
+            .map_err(|error| ProcessError::WriteError(format!("combined output BED file: {}", error)))?;
+    }
+    stats.bed_writing_time += bed_writing_start.elapsed();
+    Ok(stats)
+}
+
+fn record_name(record: &FastaRecord) -> Result<&str, ProcessError> {
+    record
+        .definition()
+        .name()
+        .to_str()
+        .map_err(|_| ProcessError::NonUtf8Name(record.definition().name().to_string()))
+}
+
+/// Lazily computes the statistics of the records from the reader, one record at a time and without writing any files.
+/// A read error is returned as [ProcessError::ReadError] and ends the iteration.
+pub fn statistics_iter<R: BufRead>(
+    mut reader: fasta::io::Reader<R>,
+    options: StatsOptions,
+) -> impl Iterator<Item = Result<SequenceStatistics, ProcessError>> {
+    let mut failed = false;
+    std::iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        let stats = match reader.records().next()? {
+            Ok(record) => record_name(&record)
+                .and_then(|name| compute_statistics(name, record.sequence().as_ref(), &options)),
+            Err(error) => {
+                failed = true;
+                Err(ProcessError::ReadError(error.to_string()))
+            }
+        };
+        Some(stats)
+    })
+}
+
+/// BED writers for the masking regions of a single sequence, writing to sinks of type `W` (e.g., plain or gzipped files).
+struct MaskRegionWriters<W: Write> {
+    non_masked: Option<BedWriter<3, BufWriter<W>>>,
+    soft_masked: Option<BedWriter<3, BufWriter<W>>>,
+    hard_masked: Option<BedWriter<3, BufWriter<W>>>,
+    /// Soft- and hard-masked regions, merged into a single category, see [masked_intervals].
+    masked: Option<BedWriter<3, BufWriter<W>>>,
+    /// Regions for the combined BED file are buffered per record, so that they are not interleaved with other records.
+    combined: Option<BedWriter<4, Vec<u8>>>,
+    /// The masked fraction per window is written once the whole sequence is scanned.
+    mask_bedgraph: Option<BufWriter<File>>,
+    gc_skew_bedgraph: Option<BufWriter<File>>,
+    mask_track: Option<BufWriter<File>>,
+    /// Output directory, file stem, and extension of the BED files, to report their paths on write errors.
+    bed_files: Option<(PathBuf, String, &'static str)>,
+}
+
+impl<W: Write> MaskRegionWriters<W> {
+    /// Creates a BED writer per masking category in the output directory (if any), wrapping each file via `wrap_file`,
+    /// as well as one for the merged soft- and hard-masked regions if `merged` is set.
+    fn create(
+        output_dir: Option<&Path>,
+        file_stem: &str,
+        extension: &'static str,
+        append: bool,
+        combined: bool,
+        merged: bool,
+        wrap_file: impl Fn(File) -> W,
+    ) -> Result<Self, std::io::Error> {
+        let create = |bed_ending| create_bed_writer(output_dir, bed_ending, file_stem, extension, append, &wrap_file);
+        Ok(MaskRegionWriters {
+            non_masked: create("non-masked")?,
+            soft_masked: create("soft-masked")?,
+            hard_masked: create("hard-masked")?,
+            masked: if merged { create("masked")? } else { None },
+            combined: combined.then(|| BedWriter::<4, _>::new(Vec::new())),
+            mask_bedgraph: None,
+            gc_skew_bedgraph: None,
+            mask_track: None,
+            bed_files: output_dir.map(|output_dir| (output_dir.to_path_buf(), file_stem.to_string(), extension)),
+        })
+    }
+
+    /// Wraps an error on writing the BED file with the given ending (e.g., `soft-masked`) into a
+    /// [ProcessError::WriteError] that names the file.
+    fn write_error(&self, bed_ending: &str, error: std::io::Error) -> ProcessError {
+        match &self.bed_files {
+            Some((output_dir, file_stem, extension)) => {
+                let output_path = bed_file_path(output_dir, file_stem, bed_ending, extension);
+                ProcessError::WriteError(format!("'{}': {}", output_path.display(), error))
+            }
+            None => ProcessError::WriteError(format!("{} BED file: {}", bed_ending, error)),
+        }
+    }
+
+    /// Flushes the BED files and finishes each of them via `finish_file` (e.g., writing the gzip trailer), so that
+    /// errors are returned instead of being ignored when dropping the writers. The bedGraph and mask track files are
+    /// already flushed once written.
+    fn close(mut self, finish_file: impl Fn(W) -> Result<(), std::io::Error>) -> Result<(), ProcessError> {
+        let bed_writers = [
+            ("non-masked", self.non_masked.take()),
+            ("soft-masked", self.soft_masked.take()),
+            ("hard-masked", self.hard_masked.take()),
+            ("masked", self.masked.take()),
+        ];
+        for (bed_ending, writer) in bed_writers {
+            if let Some(writer) = writer {
+                writer
+                    .into_inner()
+                    .into_inner()
+                    .map_err(IntoInnerError::into_error)
+                    .and_then(&finish_file)
+                    .map_err(|error| self.write_error(bed_ending, error))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Default for MaskRegionWriters<W> {
+    fn default() -> Self {
+        MaskRegionWriters {
+            non_masked: None,
+            soft_masked: None,
+            hard_masked: None,
+            masked: None,
+            combined: None,
+            mask_bedgraph: None,
+            gc_skew_bedgraph: None,
+            mask_track: None,
+            bed_files: None,
+        }
+    }
+}
+
+/// Computes the statistics of a single sequence, without reading or writing any files.
+pub fn compute_statistics(
+    name: &str,
+    sequence: &[u8],
+    options: &StatsOptions,
+) -> Result<SequenceStatistics, ProcessError> {
+    compute_sequence_statistics(name, sequence, options, &mut MaskRegionWriters::<File>::default())
+}
+
+fn compute_sequence_statistics<W: Write>(
+    name: &str,
+    sequence: &[u8],
+    options: &StatsOptions,
+    writers: &mut MaskRegionWriters<W>,
+) -> Result<SequenceStatistics, ProcessError> {
+    // Report empty sequences with all statistics set to zero, as well as all sequences if only checksums are computed.
+    if sequence.is_empty() || options.checksum_only {
+        if options.strict
+            && let Some(position) = sequence.iter().position(u8::is_ascii_lowercase)
+        {
+            return Err(ProcessError::UnexpectedSoftMask { position, sequence: name.to_string() });
+        }
+        let gap_dash_bases = sequence.iter().filter(|base| options.alignment_mode && **base == b'-').count();
+        let checksums = if options.no_checksum {
+            Checksums::default()
+        } else if sequence.is_empty() {
+            Checksums { canonical: options.canonical_checksum.then(String::new), ..Checksums::empty(options.checksum_kind) }
+        } else {
+            compute_checksums(sequence, options)
+        };
+        if let Some(matrix) = &options.position_frequency_matrix {
+            matrix.lock().unwrap().add(name, sequence)?;
+        }
+        return Ok(SequenceStatistics {
+            sequence_name: name.to_string(),
+            description: None,
+            source_file: "".to_string(),
+            non_masked_bases: 0,
+            soft_masked_bases: 0,
+            hard_masked_bases: 0,
+            non_masked_ratio: 0.0,
+            soft_masked_ratio: 0.0,
+            hard_masked_ratio: 0.0,
+            non_masked_region_count: 0,
+            soft_masked_region_count: 0,
+            hard_masked_region_count: 0,
+            soft_mask_region_mean_length: 0.0,
+            soft_mask_region_max_length: 0,
+            soft_mask_region_lengths: options.soft_mask_region_lengths.then(Vec::new),
+            gc_content: 0.0,
+            gc_content_no_n: 0.0,
+            gc_content_masked: 0.0,
+            gc_content_unmasked: 0.0,
+            gc_content_weighted: 0.0,
+            at_content: 0.0,
+            gc_skew: 0.0,
+            at_skew: 0.0,
+            gc_skew_min_position: None,
+            gc_skew_max_position: None,
+            iupac_ambiguous_bases: 0,
+            other_bases: 0,
+            a_count: 0,
+            c_count: 0,
+            g_count: 0,
+            t_count: 0,
+            n_count: 0,
+            shannon_entropy: 0.0,
+            longest_homopolymer: 0,
+            longest_homopolymer_base: '.',
+            num_gaps: 0,
+            gap_lengths: Vec::new(),
+            longest_ungapped_run: 0,
+            dinucleotide_counts: options.dinucleotides.then(|| dinucleotide_count_map(&[0; 16])),
+            cpg_count: options.dinucleotides.then_some(0),
+            cpg_observed_expected_ratio: options.dinucleotides.then_some(0.0),
+            tm_celsius: None,
+            sequence_length: sequence.len() - gap_dash_bases,
+            gap_dash_bases: options.alignment_mode.then_some(gap_dash_bases),
+            alignment_length: options.alignment_mode.then_some(sequence.len()),
+            checksum_sha256: checksums.sha256.unwrap_or_default(),
+            checksum_md5: checksums.md5,
+            checksum_blake3: checksums.blake3,
+            checksum_canonical: checksums.canonical,
+            processing_ms: None,
+            unexpected_bases: Vec::new(),
+            bed_writing_time: Duration::ZERO,
+            output_files: Vec::new(),
+        });
+    }
+
+    // The checksum(s) are computed over the whole sequence while its chunks are scanned in parallel.
+    let (checksums, stats) = rayon::join(
+        || compute_checksums(sequence, options),
+        || scan_sequence(name, sequence, PARALLEL_CHUNK_SIZE, options),
+    );
+    let stats = stats?;
+
+    let bed_writing_start = Instant::now();
+    for region in &stats.regions {
+        let writer = match region.category {
+            MaskCategory::Unmasked => writers.non_masked.as_mut(),
+            MaskCategory::Soft => writers.soft_masked.as_mut(),
+            MaskCategory::Hard => writers.hard_masked.as_mut(),
+        };
+        let written = writer.is_some() || writers.combined.is_some();
+        if let Some(writer) = writer {
+            write_bed_record(writer, name, region.category.name(), region.start, region.end, options.bed_columns)
+                .map_err(|error| writers.write_error(region.category.name(), error))?;
+        }
+        // The combined BED file always contains (at least) the masking category.
+        if let Some(combined_writer) = writers.combined.as_mut() {
+            let columns = options.bed_columns.max(BedColumns::Four);
+            write_bed_record(combined_writer, name, region.category.name(), region.start, region.end, columns)
+                .map_err(|error| ProcessError::WriteError(format!("combined output BED file: {}", error)))?;
+        }
+        if written {
+            debug!("Wrote {} region {}:{}-{}.", region.category.name(), name, region.start, region.end);
+        }
+    }
+    if let Some(writer) = writers.masked.as_mut() {
+        masked_intervals(&stats.regions)
+            .into_iter()
+            .try_for_each(|(start, end)| write_bed_record(writer, name, "masked", start, end, options.bed_columns))
+            .map_err(|error| writers.write_error("masked", error))?;
+    }
+    if let (Some(writer), Some(window)) = (writers.mask_bedgraph.as_mut(), options.mask_window) {
+        write_mask_bedgraph(writer, name, sequence.len(), window, &stats.masked_window_counts)
+            .map_err(|error| ProcessError::WriteError(error.to_string()))?;
+    }
+    let cumulative_gc_skews = cumulative_gc_skews(&stats.gc_skew_window_counts);
+    if let (Some(writer), Some(window)) = (writers.gc_skew_bedgraph.as_mut(), options.gc_skew_window) {
+        write_gc_skew_bedgraph(writer, name, sequence.len(), window, &cumulative_gc_skews)
+            .map_err(|error| ProcessError::WriteError(error.to_string()))?;
+    }
+    if let Some(writer) = writers.mask_track.as_mut() {
+        write_mask_track(writer, sequence.len(), &masked_intervals(&stats.regions))
+            .map_err(|error| ProcessError::WriteError(error.to_string()))?;
+    }
+    let bed_writing_time = bed_writing_start.elapsed();
+    // Ties are resolved in favor of the first window, and positions refer to the end of the window.
+    let window_end = |index: usize| options.gc_skew_window.map(|window| sequence.len().min((index + 1) * window));
+    let gc_skew_min_position = (0..cumulative_gc_skews.len())
+        .reduce(|min, index| if cumulative_gc_skews[index] < cumulative_gc_skews[min] { index } else { min })
+        .and_then(window_end);
+    let gc_skew_max_position = (0..cumulative_gc_skews.len())
+        .reduce(|max, index| if cumulative_gc_skews[index] > cumulative_gc_skews[max] { index } else { max })
+        .and_then(window_end);
+    let region_count = |category| stats.regions.iter().filter(|region| region.category == category).count();
+    // Hard-masked regions are exactly the gaps (runs of N, or only of uppercase N with split_n_case).
+    let gap_lengths: Vec<usize> = stats
+        .regions
+        .iter()
+        .filter(|region| region.category == MaskCategory::Hard)
+        .map(|region| region.end - region.start)
+        .collect();
+    let soft_mask_region_lengths: Vec<usize> = stats
+        .regions
+        .iter()
+        .filter(|region| region.category == MaskCategory::Soft)
+        .map(|region| region.end - region.start)
+        .collect();
+    // The ungapped runs are the stretches between the gaps (and the ends of the sequence).
+    let mut longest_ungapped_run = 0;
+    let mut run_start = 0;
+    for gap in stats.regions.iter().filter(|region| region.category == MaskCategory::Hard) {
+        longest_ungapped_run = longest_ungapped_run.max(gap.start - run_start);
+        run_start = gap.end;
+    }
+    longest_ungapped_run = longest_ungapped_run.max(sequence.len() - run_start);
+
+    // Ambiguous IUPAC codes and other bytes are neither counted as a base nor assigned to a masking category.
+    // Alignment gaps are only counted in the alignment length, not in the sequence length.
+    let other_counter = stats.iupac_ambiguous_bases_counter + stats.other_bases_counter + stats.gap_dash_counter;
+    let sequence_length = sequence.len() - stats.gap_dash_counter;
+    assert!(
+        stats.non_mask_counter + stats.soft_mask_counter + stats.hard_mask_counter + other_counter == sequence.len(),
+        "The sum of masked bases does not match the sequence length ({}) for '{}'. This seems to be a bug.",
+        sequence.len(),
+        name
+    );
+    assert!(
+        stats.a_counter + stats.c_counter + stats.g_counter + stats.t_counter + stats.n_counter + other_counter
+            == sequence.len(),
+        "The sum of base counts does not match the sequence length ({}) for '{}'. This seems to be a bug.",
+        sequence.len(),
+        name
+    );
+    let (a_counter, c_counter, g_counter, t_counter) = (stats.a_counter, stats.c_counter, stats.g_counter, stats.t_counter);
+    let dinucleotide_counts = stats.dinucleotide_counts;
+    if let Some(matrix) = &options.position_frequency_matrix {
+        matrix.lock().unwrap().add(name, sequence)?;
+    }
+    Ok(SequenceStatistics {
+        sequence_name: name.to_string(),
+        description: None,
+        source_file: "".to_string(),
+        non_masked_bases: stats.non_mask_counter,
+        soft_masked_bases: stats.soft_mask_counter,
+        hard_masked_bases: stats.hard_mask_counter,
+        non_masked_ratio: safe_ratio(stats.non_mask_counter, sequence_length),
+        soft_masked_ratio: safe_ratio(stats.soft_mask_counter, sequence_length),
+        hard_masked_ratio: safe_ratio(stats.hard_mask_counter, sequence_length),
+        non_masked_region_count: region_count(MaskCategory::Unmasked),
+        soft_masked_region_count: soft_mask_region_lengths.len(),
+        hard_masked_region_count: gap_lengths.len(),
+        soft_mask_region_mean_length: safe_ratio(stats.soft_mask_counter, soft_mask_region_lengths.len()),
+        soft_mask_region_max_length: soft_mask_region_lengths.iter().copied().max().unwrap_or(0),
+        soft_mask_region_lengths: options.soft_mask_region_lengths.then_some(soft_mask_region_lengths),
+        gc_content: if !options.iupac_aware {
+            safe_ratio(stats.gc_counter, sequence_length)
+        } else if stats.gc_contribution_counter > 0 {
+            stats.gc_contribution_sum / stats.gc_contribution_counter as f64
+        } else {
+            0.0
+        },
+        gc_content_no_n: safe_ratio(stats.gc_counter, a_counter + c_counter + g_counter + t_counter),
+        gc_content_masked: safe_ratio(stats.masked_gc_counter, stats.soft_mask_counter + stats.hard_mask_counter),
+        gc_content_unmasked: safe_ratio(stats.gc_counter - stats.masked_gc_counter, stats.non_mask_counter),
+        gc_content_weighted: weighted_gc_content(
+            stats.gc_counter - stats.masked_gc_counter,
+            stats.masked_gc_counter,
+            sequence_length - stats.soft_mask_counter,
+            stats.soft_mask_counter,
+            options.softmask_weight.unwrap_or(1.0),
+        ),
+        at_content: safe_ratio(a_counter + t_counter, sequence_length),
+        gc_skew: skew(g_counter, c_counter),
+        at_skew: skew(a_counter, t_counter),
+        gc_skew_min_position,
+        gc_skew_max_position,
+        iupac_ambiguous_bases: stats.iupac_ambiguous_bases_counter,
+        other_bases: stats.other_bases_counter,
+        a_count: a_counter,
+        c_count: c_counter,
+        g_count: g_counter,
+        t_count: t_counter,
+        n_count: stats.n_counter,
+        shannon_entropy: shannon_entropy(&[a_counter, c_counter, g_counter, t_counter]),
+        longest_homopolymer: stats.longest_homopolymer_length,
+        longest_homopolymer_base: stats.longest_homopolymer_base as char,
+        num_gaps: gap_lengths.len(),
+        gap_lengths,
+        longest_ungapped_run,
+        dinucleotide_counts: options.dinucleotides.then(|| dinucleotide_count_map(&dinucleotide_counts)),
+        cpg_count: options.dinucleotides.then_some(dinucleotide_counts[CPG_INDEX]),
+        cpg_observed_expected_ratio: options.dinucleotides.then(|| {
+            cpg_observed_expected_ratio(dinucleotide_counts[CPG_INDEX], a_counter, c_counter, g_counter, t_counter)
+        }),
+        tm_celsius: if options.tm {
+            melting_temperature(a_counter, c_counter, g_counter, t_counter, sequence_length)
+        } else {
+            None
+        },
+        sequence_length,
+        gap_dash_bases: options.alignment_mode.then_some(stats.gap_dash_counter),
+        alignment_length: options.alignment_mode.then_some(sequence.len()),
+        checksum_sha256: checksums.sha256.unwrap_or_default(),
+        checksum_md5: checksums.md5,
+        checksum_blake3: checksums.blake3,
+        checksum_canonical: checksums.canonical,
+        processing_ms: None,
+        unexpected_bases: stats.unexpected_bases,
+        bed_writing_time,
+        output_files: Vec::new(),
+    })
+}
+
+/// Scans the sequence in chunks of the given size (in parallel if there are several) and merges their statistics.
+fn scan_sequence(
+    name: &str,
+    sequence: &[u8],
+    chunk_size: usize,
+    options: &StatsOptions,
+) -> Result<ChunkStatistics, ProcessError> {
+    let chunks: Vec<Result<ChunkStatistics, ProcessError>> = if sequence.len() <= chunk_size {
+        vec![compute_chunk_statistics(name, sequence, 0, options)]
+    } else {
+        sequence
+            .par_chunks(chunk_size)
+            .enumerate()
+            .map(|(index, chunk)| compute_chunk_statistics(name, chunk, index * chunk_size, options))
+            .collect()
+    };
+    let mut stats = ChunkStatistics::default();
+    for chunk in chunks {
+        stats.append(chunk?);
+    }
+    Ok(stats)
+}
+
+/// Scans a chunk of a sequence that starts at the given (0-based) offset.
+fn compute_chunk_statistics(
+    name: &str,
+    chunk: &[u8],
+    offset: usize,
+    options: &StatsOptions,
+) -> Result<ChunkStatistics, ProcessError> {
+    let mut stats = ChunkStatistics {
+        length: chunk.len(),
+        homopolymer_base: b'.',
+        longest_homopolymer_base: b'.',
+        ..Default::default()
+    };
+    if let Some(window) = options.mask_window
+        && !chunk.is_empty()
+    {
+        stats.first_mask_window = offset / window;
+        stats.masked_window_counts = vec![0; (offset + chunk.len() - 1) / window - stats.first_mask_window + 1];
+    }
+    if let Some(window) = options.gc_skew_window
+        && !chunk.is_empty()
+    {
+        stats.first_gc_skew_window = offset / window;
+        stats.gc_skew_window_counts = vec![(0, 0); (offset + chunk.len() - 1) / window - stats.first_gc_skew_window + 1];
+    }
+    let mut region: Option<MaskRegion> = None;
+    let mut previous_nucleotide_index: Option<usize> = None;
+    let is_mask_char = mask_char_table(options.mask_chars.as_deref().unwrap_or(DEFAULT_MASK_CHARS));
+
+    for (index, original_base) in chunk.iter().enumerate() {
+        if options.strict && original_base.is_ascii_lowercase() {
+            return Err(ProcessError::UnexpectedSoftMask { position: offset + index, sequence: name.to_string() });
+        }
+        let base = &options.alphabet.dna_base(*original_base);
+        if options.iupac_aware && let Some(contribution) = gc_contribution(*base) {
+            stats.gc_contribution_sum += contribution;
+            stats.gc_contribution_counter += 1;
+        }
+        match base.to_ascii_uppercase() {
+            b'A' => stats.a_counter += 1,
+            b'C' => stats.c_counter += 1,
+            b'G' => stats.g_counter += 1,
+            b'T' => stats.t_counter += 1,
+            // Alignment gaps are only counted as such, even if `-` is a mask character.
+            b'-' if options.alignment_mode => {}
+            _ if is_mask_char[*base as usize] => stats.n_counter += 1,
+            _ => {}
+        }
+        if options.dinucleotides {
+            let nucleotide_index = NUCLEOTIDES.iter().position(|nucleotide| *nucleotide == base.to_ascii_uppercase());
+            if let (Some(previous_index), Some(index)) = (previous_nucleotide_index, nucleotide_index) {
+                stats.dinucleotide_counts[previous_index * 4 + index] += 1;
+            }
+            if index == 0 {
+                stats.first_nucleotide_index = nucleotide_index;
+            }
+            previous_nucleotide_index = nucleotide_index;
+        }
+        update_homopolymer_run(
+            base.to_ascii_uppercase(),
+            &mut stats.homopolymer_base,
+            &mut stats.homopolymer_length,
+            &mut stats.longest_homopolymer_base,
+            &mut stats.longest_homopolymer_length,
+        );
+        if stats.homopolymer_length == index + 1 {
+            stats.leading_homopolymer_base = stats.homopolymer_base;
+            stats.leading_homopolymer_length = stats.homopolymer_length;
+        }
+        let category = match *base {
+            b'-' if options.alignment_mode => {
+                stats.gap_dash_counter += 1;
+                None
+            }
+            b'c' | b'g' if options.no_soft_mask => {
+                stats.gc_counter += 1;
+                stats.non_mask_counter += 1;
+                Some(MaskCategory::Unmasked)
+            }
+            b'a' | b't' if options.no_soft_mask => {
+                stats.non_mask_counter += 1;
+                Some(MaskCategory::Unmasked)
+            }
+            b'C' | b'G' => {
+                stats.gc_counter += 1;
+                stats.non_mask_counter += 1;
+                Some(MaskCategory::Unmasked)
+            }
+            b'c' | b'g' => {
+                stats.gc_counter += 1;
+                stats.masked_gc_counter += 1;
+                stats.soft_mask_counter += 1;
+                Some(MaskCategory::Soft)
+            }
+            b'A' | b'T' => {
+                stats.non_mask_counter += 1;
+                Some(MaskCategory::Unmasked)
+            }
+            b'a' | b't' => {
+                stats.soft_mask_counter += 1;
+                Some(MaskCategory::Soft)
+            }
+            b'n' if options.split_n_case && is_mask_char[b'n' as usize] => {
+                stats.soft_mask_counter += 1;
+                Some(MaskCategory::Soft)
+            }
+            _ if is_mask_char[*base as usize] => {
+                stats.hard_mask_counter += 1;
+                Some(MaskCategory::Hard)
+            }
+            _ if (options.ignore_iupac || options.iupac_aware) && is_unsupported_iupac_code(*base) => {
+                stats.iupac_ambiguous_bases_counter += 1;
+                None
+            }
+            _ if options.ignore_iupac => {
+                stats.other_bases_counter += 1;
+                None
+            }
+            _ => {
+                return Err(ProcessError::UnexpectedBase {
+                    base: *original_base,
+                    sequence: name.to_string(),
+                });
+            }
+        };
+
+        // Extend the current region, or close it and start a new one if the category changes.
+        let position = offset + index;
+        // Only bases other than `ACGT` and the mask characters have no masking category.
+        if category.is_none()
+            && !(options.alignment_mode && *base == b'-')
+            && options.report_unexpected
+            && stats.unexpected_bases.len() < MAX_REPORTED_UNEXPECTED_BASES
+        {
+            stats.unexpected_bases.push((position, *original_base));
+        }
+        if let Some(window) = options.mask_window
+            && matches!(category, Some(MaskCategory::Soft | MaskCategory::Hard))
+        {
+            stats.masked_window_counts[position / window - stats.first_mask_window] += 1;
+        }
+        if let Some(window) = options.gc_skew_window {
+            let (g_count, c_count) = &mut stats.gc_skew_window_counts[position / window - stats.first_gc_skew_window];
+            match base.to_ascii_uppercase() {
+                b'G' => *g_count += 1,
+                b'C' => *c_count += 1,
+                _ => {}
+            }
+        }
+        match &mut region {
+            Some(current) if Some(current.category) == category => current.end = position + 1,
+            _ => {
+                stats.regions.extend(region.take());
+                region = category.map(|category| MaskRegion { category, start: position, end: position + 1 });
+            }
+        }
+    }
+    stats.regions.extend(region);
+    stats.last_nucleotide_index = previous_nucleotide_index;
+    Ok(stats)
+}
+
+/// Merges adjacent soft- and hard-masked regions into masked intervals (0-based, half-open). Regions separated by
+/// non-masked bases or by bases without masking category (e.g., ignored IUPAC codes) are kept apart.
+fn masked_intervals(regions: &[MaskRegion]) -> Vec<(usize, usize)> {
+    let mut intervals: Vec<(usize, usize)> = Vec::new();
+    for region in regions.iter().filter(|region| region.category != MaskCategory::Unmasked) {
+        match intervals.last_mut() {
+            Some((_, end)) if *end == region.start => *end = region.end,
+            _ => intervals.push((region.start, region.end)),
+        }
+    }
+    intervals
+}
+
+/// Sequences longer than this are split into chunks of this size, which are processed in parallel.
+const PARALLEL_CHUNK_SIZE: usize = 1 << 22;
+
+/// Size of the blocks that are converted to upper case before being fed into the checksum(s).
+const CHECKSUM_BLOCK_SIZE: usize = 1 << 16;
+
+/// Computes the selected checksum(s) over the whole sequence, see [SequenceStatistics::checksum_sha256].
+fn compute_checksums(sequence: &[u8], options: &StatsOptions) -> Checksums {
+    if options.no_checksum {
+        return Checksums::default();
+    }
+    let mut hasher = SequenceHasher::new(options.checksum_kind);
+    if options.checksum_uppercase {
+        for block in sequence.chunks(CHECKSUM_BLOCK_SIZE) {
+            hasher.update(&block.to_ascii_uppercase());
+        }
+    } else {
+        hasher.update(sequence);
+    }
+    Checksums { canonical: options.canonical_checksum.then(|| canonical_checksum(sequence)), ..hasher.finalize() }
+}
+
+/// Complements a base (case-insensitive), i.e., `A` <-> `T` and `C` <-> `G`. All other bytes, e.g., `N`, are kept as
+/// they are. The result is always in upper case.
+fn complement(base: u8) -> u8 {
+    match base.to_ascii_uppercase() {
+        b'A' => b'T',
+        b'T' => b'A',
+        b'C' => b'G',
+        b'G' => b'C',
+        other => other,
+    }
+}
+
+/// Computes the hex-encoded SHA256 checksum of the uppercase sequence or of its reverse complement (see [complement]),
+/// whichever is lexicographically smaller, so that both strands of a sequence have the same checksum.
+pub fn canonical_checksum(sequence: &[u8]) -> String {
+    let forward = sequence.iter().map(u8::to_ascii_uppercase);
+    let reverse_complement = sequence.iter().rev().map(|base| complement(*base));
+    let mut checksum = Box::new(Sha256Checksum::default());
+    if reverse_complement.lt(forward) {
+        for block in sequence.rchunks(CHECKSUM_BLOCK_SIZE) {
+            checksum.update(&block.iter().rev().map(|base| complement(*base)).collect::<Vec<u8>>());
+        }
+    } else {
+        for block in sequence.chunks(CHECKSUM_BLOCK_SIZE) {
+            checksum.update(&block.to_ascii_uppercase());
+        }
+    }
+    checksum.finalize()
+}
+
+/// The masking category of a base.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MaskCategory {
+    Unmasked,
+    Soft,
+    Hard,
+}
+
+impl MaskCategory {
+    fn name(self) -> &'static str {
+        match self {
+            MaskCategory::Unmasked => "non-masked",
+            MaskCategory::Soft => "soft-masked",
+            MaskCategory::Hard => "hard-masked",
+        }
+    }
+}
+
+/// A maximal run of bases with the same masking category, from `start` (0-based, inclusive) to `end` (exclusive).
+#[derive(Debug, Clone, PartialEq)]
+struct MaskRegion {
+    category: MaskCategory,
+    start: usize,
+    end: usize,
+}
+
+/// The counters of a chunk of a sequence. Chunks can be scanned independently and are then merged in order, see
+/// [ChunkStatistics::append].
+#[derive(Debug, Default, PartialEq)]
+struct ChunkStatistics {
+    length: usize,
+    gc_counter: usize,
+    /// Number of soft-masked `c`/`g` bases (hard-masked bases are never `G`/`C`).
+    masked_gc_counter: usize,
+    gc_contribution_sum: f64,
+    gc_contribution_counter: usize,
+    iupac_ambiguous_bases_counter: usize,
+    other_bases_counter: usize,
+    gap_dash_counter: usize,
+    a_counter: usize,
+    c_counter: usize,
+    g_counter: usize,
+    t_counter: usize,
+    n_counter: usize,
+    non_mask_counter: usize,
+    soft_mask_counter: usize,
+    hard_mask_counter: usize,
+    dinucleotide_counts: [usize; 16],
+    /// Indices of the first and last base in [NUCLEOTIDES] (if counting dinucleotides), to count the dinucleotide
+    /// spanning two chunks.
+    first_nucleotide_index: Option<usize>,
+    last_nucleotide_index: Option<usize>,
+    /// Homopolymer run at the start of the chunk, which may continue the run at the end of the previous chunk.
+    leading_homopolymer_base: u8,
+    leading_homopolymer_length: usize,
+    homopolymer_base: u8,
+    homopolymer_length: usize,
+    longest_homopolymer_base: u8,
+    longest_homopolymer_length: usize,
+    regions: Vec<MaskRegion>,
+    /// Number of soft- and hard-masked bases per window (if writing the masked fraction), starting with the window
+    /// that contains the first base of the chunk.
+    first_mask_window: usize,
+    masked_window_counts: Vec<usize>,
+    /// Number of `G` and `C` bases per window (if writing the cumulative GC skew), like [Self::masked_window_counts].
+    first_gc_skew_window: usize,
+    gc_skew_window_counts: Vec<(usize, usize)>,
+    /// Positions and bytes of the bases other than `ACGTN` (if reporting them), at most
+    /// [MAX_REPORTED_UNEXPECTED_BASES].
+    unexpected_bases: Vec<(usize, u8)>,
+}
+
+impl ChunkStatistics {
+    /// Appends the statistics of the next chunk, joining the homopolymer runs and masking regions that span both.
+    fn append(&mut self, next: ChunkStatistics) {
+        if self.length == 0 {
+            *self = next;
+            return;
+        }
+        self.gc_counter += next.gc_counter;
+        self.masked_gc_counter += next.masked_gc_counter;
+        self.gc_contribution_sum += next.gc_contribution_sum;
+        self.gc_contribution_counter += next.gc_contribution_counter;
+        self.iupac_ambiguous_bases_counter += next.iupac_ambiguous_bases_counter;
+        self.other_bases_counter += next.other_bases_counter;
+        self.gap_dash_counter += next.gap_dash_counter;
+        self.a_counter += next.a_counter;
+        self.c_counter += next.c_counter;
+        self.g_counter += next.g_counter;
+        self.t_counter += next.t_counter;
+        self.n_counter += next.n_counter;
+        self.non_mask_counter += next.non_mask_counter;
+        self.soft_mask_counter += next.soft_mask_counter;
+        self.hard_mask_counter += next.hard_mask_counter;
+
+        for (count, next_count) in self.dinucleotide_counts.iter_mut().zip(next.dinucleotide_counts) {
+            *count += next_count;
+        }
+        if let (Some(last_index), Some(first_index)) = (self.last_nucleotide_index, next.first_nucleotide_index) {
+            self.dinucleotide_counts[last_index * 4 + first_index] += 1;
+        }
+        self.last_nucleotide_index = next.last_nucleotide_index;
+
+        // Runs are compared with `>`, so that the first of several longest runs is kept.
+        let joins_run = next.leading_homopolymer_length > 0 && next.leading_homopolymer_base == self.homopolymer_base;
+        if joins_run {
+            let joined_length = self.homopolymer_length + next.leading_homopolymer_length;
+            if joined_length > self.longest_homopolymer_length {
+                self.longest_homopolymer_base = self.homopolymer_base;
+                self.longest_homopolymer_length = joined_length;
+            }
+        }
+        if next.longest_homopolymer_length > self.longest_homopolymer_length {
+            self.longest_homopolymer_base = next.longest_homopolymer_base;
+            self.longest_homopolymer_length = next.longest_homopolymer_length;
+        }
+        if joins_run && self.leading_homopolymer_length == self.length {
+            self.leading_homopolymer_length += next.leading_homopolymer_length;
+        }
+        if joins_run && next.leading_homopolymer_length == next.length {
+            self.homopolymer_length += next.length;
+        } else {
+            self.homopolymer_base = next.homopolymer_base;
+            self.homopolymer_length = next.homopolymer_length;
+        }
+
+        let mut next_regions = next.regions.into_iter().peekable();
+        if let (Some(last), Some(first)) = (self.regions.last_mut(), next_regions.peek())
+            && last.category == first.category
+            && last.end == first.start
+        {
+            last.end = first.end;
+            next_regions.next();
+        }
+        self.regions.extend(next_regions);
+
+        // Unless the chunks are split at a window boundary, the last window of this chunk continues in the next one.
+        let mut next_window_counts = next.masked_window_counts.into_iter();
+        if self.first_mask_window + self.masked_window_counts.len() > next.first_mask_window
+            && let (Some(last), Some(first)) = (self.masked_window_counts.last_mut(), next_window_counts.next())
+        {
+            *last += first;
+        }
+        self.masked_window_counts.extend(next_window_counts);
+        let mut next_gc_skew_counts = next.gc_skew_window_counts.into_iter();
+        if self.first_gc_skew_window + self.gc_skew_window_counts.len() > next.first_gc_skew_window
+            && let (Some(last), Some(first)) = (self.gc_skew_window_counts.last_mut(), next_gc_skew_counts.next())
+        {
+            *last = (last.0 + first.0, last.1 + first.1);
+        }
+        self.gc_skew_window_counts.extend(next_gc_skew_counts);
+
+        let free_capacity = MAX_REPORTED_UNEXPECTED_BASES.saturating_sub(self.unexpected_bases.len());
+        self.unexpected_bases.extend(next.unexpected_bases.into_iter().take(free_capacity));
+        self.length += next.length;
+    }
+}
+
+
+/// Counts the `G`/`C` bases and all `ACGT` bases (case-insensitive, with `U` handled according to the alphabet) of a
+/// part of a sequence, i.e., without the mask characters and any other bases, like for
+/// [SequenceStatistics::gc_content_no_n].
+fn gc_and_nucleotide_counts(bases: &[u8], alphabet: Alphabet) -> (usize, usize) {
+    let mut counts = (0, 0);
+    for base in bases {
+        match alphabet.dna_base(*base).to_ascii_uppercase() {
+            b'G' | b'C' => counts = (counts.0 + 1, counts.1 + 1),
+            b'A' | b'T' => counts.1 += 1,
+            _ => {}
+        }
+    }
+    counts
+}
+
+/// Writes the GC content of all non-overlapping windows of the given size (the last one may be shorter) in bedGraph
+/// format. The GC content of a window only considers `ACGT` (see [gc_and_nucleotide_counts]), and windows without any
+/// of them (e.g., only consisting of `N`) are skipped.
+pub fn write_gc_bedgraph<W: Write>(
+    writer: &mut W,
+    sequence_name: &str,
+    sequence: &[u8],
+    window: usize,
+    alphabet: Alphabet,
+) -> Result<(), std::io::Error> {
+    for (window_index, window_sequence) in sequence.chunks(window).enumerate() {
+        let (gc_bases, nucleotides) = gc_and_nucleotide_counts(window_sequence, alphabet);
+        if nucleotides == 0 {
+            continue;
+        }
+        let start = window_index * window;
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}",
+            sequence_name,
+            start,
+            start + window_sequence.len(),
+            gc_bases as f64 / nucleotides as f64
+        )?;
+    }
+    writer.flush()
+}
+
+/// Writes the GC content of the sequence divided into the given number of bins (of `length / bins` bases each, with
+/// the last one absorbing the remainder) as TSV with the columns `bin_index` and `gc_fraction`. Like in
+/// [write_gc_bedgraph], only `ACGT` are considered; bins without any of them (or empty ones) have GC content `NA`.
+pub fn write_gc_profile<W: Write>(
+    writer: &mut W,
+    sequence: &[u8],
+    bins: usize,
+    alphabet: Alphabet,
+) -> Result<(), std::io::Error> {
+    let bin_size = sequence.len() / bins;
+    writeln!(writer, "bin_index\tgc_fraction")?;
+    for bin_index in 0..bins {
+        let end = if bin_index + 1 == bins { sequence.len() } else { (bin_index + 1) * bin_size };
+        let bin_sequence = &sequence[bin_index * bin_size..end];
+        let (gc_bases, nucleotides) = gc_and_nucleotide_counts(bin_sequence, alphabet);
+        if nucleotides == 0 {
+            writeln!(writer, "{}\tNA", bin_index)?;
+        } else {
+            writeln!(writer, "{}\t{}", bin_index, gc_bases as f64 / nucleotides as f64)?;
+        }
+    }
+    writer.flush()
+}
+
+/// Writes the fraction of soft- and hard-masked bases of all non-overlapping windows of the given size (the last one
+/// may be shorter) in bedGraph format, given the number of masked bases per window.
+fn write_mask_bedgraph<W: Write>(
+    writer: &mut W,
+    sequence_name: &str,
+    sequence_length: usize,
+    window: usize,
+    masked_window_counts: &[usize],
+) -> Result<(), std::io::Error> {
+    for (window_index, masked_bases) in masked_window_counts.iter().enumerate() {
+        let start = window_index * window;
+        let end = sequence_length.min(start + window);
+        writeln!(writer, "{}\t{}\t{}\t{}", sequence_name, start, end, *masked_bases as f64 / (end - start) as f64)?;
+    }
+    writer.flush()
+}
+
+/// Sums up the GC skew `(G - C) / (G + C)` of the windows from the 5' end, given the number of `G` and `C` per window.
+fn cumulative_gc_skews(gc_skew_window_counts: &[(usize, usize)]) -> Vec<f64> {
+    gc_skew_window_counts
+        .iter()
+        .scan(0.0, |sum, (g_count, c_count)| {
+            *sum += skew(*g_count, *c_count);
+            Some(*sum)
+        })
+        .collect()
+}
+
+/// Writes the cumulative GC skew (see [cumulative_gc_skews]) at the end of all non-overlapping windows of the given
+/// size (the last one may be shorter) in bedGraph format. Its minimum and maximum often mark the replication terminus
+/// and origin, see [SequenceStatistics::gc_skew_min_position].
+fn write_gc_skew_bedgraph<W: Write>(
+    writer: &mut W,
+    sequence_name: &str,
+    sequence_length: usize,
+    window: usize,
+    cumulative_gc_skews: &[f64],
+) -> Result<(), std::io::Error> {
+    for (window_index, cumulative_gc_skew) in cumulative_gc_skews.iter().enumerate() {
+        let start = window_index * window;
+        let end = sequence_length.min(start + window);
+        writeln!(writer, "{}\t{}\t{}\t{}", sequence_name, start, end, cumulative_gc_skew)?;
+    }
+    writer.flush()
+}
+
+/// The first bytes of a file written by [write_mask_track].
+const MASK_TRACK_MAGIC_BYTES: &[u8; 4] = b"FMSK";
+
+/// Writes the masking of each base as a packed bit vector (`1` for soft- or hard-masked bases, `0` otherwise, the
+/// first base in the most significant bit of the first byte), after a header with [MASK_TRACK_MAGIC_BYTES] and the
+/// sequence length (as 64-bit little-endian integer). See [read_mask_track] for the corresponding reader.
+fn write_mask_track<W: Write>(
+    writer: &mut W,
+    sequence_length: usize,
+    masked_intervals: &[(usize, usize)],
+) -> Result<(), std::io::Error> {
+    let mut mask = BitVec::from_elem(sequence_length, false);
+    for (start, end) in masked_intervals {
+        for position in *start..*end {
+            mask.set(position, true);
+        }
+    }
+    writer.write_all(MASK_TRACK_MAGIC_BYTES)?;
+    writer.write_all(&(sequence_length as u64).to_le_bytes())?;
+    writer.write_all(&mask.to_bytes())?;
+    writer.flush()
+}
+
+/// Reads the masking of each base from a file written by [write_mask_track] (i.e., with `--emit-mask-track`).
+pub fn read_mask_track(path: &Path) -> Result<BitVec, std::io::Error> {
+    let content = std::fs::read(path)?;
+    let invalid = |message: &str| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, format!("'{}': {}", path.display(), message))
+    };
+    let header_length = MASK_TRACK_MAGIC_BYTES.len() + 8;
+    if content.len() < header_length || !content.starts_with(MASK_TRACK_MAGIC_BYTES) {
+        return Err(invalid("Not a mask track file."));
+    }
+    let length_bytes: [u8; 8] = content[MASK_TRACK_MAGIC_BYTES.len()..header_length].try_into().unwrap();
+    let sequence_length = u64::from_le_bytes(length_bytes) as usize;
+    let bytes = &content[header_length..];
+    if bytes.len() != sequence_length.div_ceil(8) {
+        return Err(invalid("The size of the mask track does not match the sequence length."));
+    }
+    let mut mask = BitVec::from_bytes(bytes);
+    mask.truncate(sequence_length);
+    Ok(mask)
+}
+
+/// Prepends the prefix (if not empty) to the file name, separated by a dot (e.g., `mygenome.summary.json`).
+pub fn prefixed_file_name(prefix: &str, file_name: &str) -> String {
+    if prefix.is_empty() {
+        file_name.to_string()
+    } else {
+        format!("{}.{}", prefix, file_name)
+    }
+}
+
+/// Turns a sequence name into a name that can safely be used as (part of) a file name, by replacing all characters
+/// except ASCII letters, digits, `.`, `-`, and `_` with `_`. If any character is replaced, the first eight hex digits
+/// of the SHA256 checksum of the original name are appended, so that, e.g., `a/b` and `a|b` do not end up in the same
+/// files.
+pub fn sanitize_filename(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    if sanitized == name {
+        sanitized
+    } else {
+        format!("{}_{}", sanitized, &format!("{:x}", Sha256::digest(name.as_bytes()))[..8])
+    }
+}
+
+/// Compares sequence names in natural (version) order, i.e., runs of digits are compared by their numeric value, so
+/// that `chr2` comes before `chr10`. Numbers that only differ in leading zeros are ordered by their length (`1` < `01`).
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_byte), Some(b_byte)) if a_byte.is_ascii_digit() && b_byte.is_ascii_digit() => {
+                let (a_number, a_rest) = a.split_at(a.iter().take_while(|byte| byte.is_ascii_digit()).count());
+                let (b_number, b_rest) = b.split_at(b.iter().take_while(|byte| byte.is_ascii_digit()).count());
+                let a_digits = &a_number[a_number.iter().take_while(|byte| **byte == b'0').count()..];
+                let b_digits = &b_number[b_number.iter().take_while(|byte| **byte == b'0').count()..];
+                let ordering = a_digits
+                    .len()
+                    .cmp(&b_digits.len())
+                    .then_with(|| a_digits.cmp(b_digits))
+                    .then_with(|| a_number.len().cmp(&b_number.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                (a, b) = (a_rest, b_rest);
+            }
+            (Some(a_byte), Some(b_byte)) => {
+                if a_byte != b_byte {
+                    return a_byte.cmp(b_byte);
+                }
+                (a, b) = (&a[1..], &b[1..]);
+            }
+        }
+    }
+}
+
+/// Creates (or truncates) the output file, or opens it for appending.
+fn open_output_file(output_path: &Path, append: bool) -> Result<File, std::io::Error> {
+    if append {
+        File::options().create(true).append(true).open(output_path)
+    } else {
+        File::create(output_path)
+    }
+}
+
+/// Largest k-mer size for which the counts are stored in a dense array (of `4^k` entries) instead of a hash map.
+const MAX_DENSE_KMER_SIZE: usize = 10;
+
+/// Counts the canonical k-mers (i.e., the lexicographically smaller of a k-mer and its reverse complement) of the
+/// sequence, case-insensitive and skipping all k-mers that contain other bases than `A`, `C`, `G`, or `T`. Returns the
+/// k-mers that occur at least once, in lexicographic order. The size `k` must be between 1 and 32.
+pub fn count_canonical_kmers(sequence: &[u8], k: usize) -> Vec<(String, usize)> {
+    assert!((1..=32).contains(&k), "The k-mer size must be between 1 and 32, but is {}.", k);
+    let mask: u64 = if k == 32 { u64::MAX } else { (1 << (2 * k)) - 1 };
+    let mut dense_counts: Vec<usize> = if k <= MAX_DENSE_KMER_SIZE { vec![0; 1 << (2 * k)] } else { Vec::new() };
+    let mut sparse_counts: HashMap<u64, usize> = HashMap::new();
+
+    // The k-mer and its reverse complement are encoded with 2 bits per base, and updated with each base.
+    let mut forward: u64 = 0;
+    let mut reverse: u64 = 0;
+    let mut valid_bases: usize = 0;
+    for base in sequence {
+        let Some(code) = NUCLEOTIDES.iter().position(|nucleotide| *nucleotide == base.to_ascii_uppercase()) else {
+            valid_bases = 0;
+            continue;
+        };
+        let code = code as u64;
+        forward = ((forward << 2) | code) & mask;
+        reverse = (reverse >> 2) | ((3 - code) << (2 * (k - 1)));
+        valid_bases += 1;
+        if valid_bases >= k {
+            let canonical = forward.min(reverse);
+            if k <= MAX_DENSE_KMER_SIZE {
+                dense_counts[canonical as usize] += 1;
+            } else {
+                *sparse_counts.entry(canonical).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut counts: Vec<(u64, usize)> = if k <= MAX_DENSE_KMER_SIZE {
+        dense_counts
+            .into_iter()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .map(|(kmer, count)| (kmer as u64, count))
+            .collect()
+    } else {
+        sparse_counts.into_iter().collect()
+    };
+    // As A < C < G < T, the numeric order of the encoded k-mers is their lexicographic order.
+    counts.sort_unstable();
+    counts
+        .into_iter()
+        .map(|(kmer, count)| {
+            let kmer: Vec<u8> = (0..k).rev().map(|i| NUCLEOTIDES[((kmer >> (2 * i)) & 3) as usize]).collect();
+            (String::from_utf8_lossy(&kmer).into_owned(), count)
+        })
+        .collect()
+}
+
+/// Writes the counts of the canonical k-mers of the sequence (see [count_canonical_kmers]) as tab-separated lines.
+pub fn write_kmer_counts<W: Write>(writer: &mut W, sequence: &[u8], k: usize) -> Result<(), std::io::Error> {
+    for (kmer, count) in count_canonical_kmers(sequence, k) {
+        writeln!(writer, "{}\t{}", kmer, count)?;
+    }
+    writer.flush()
+}
+
+/// The path of the BED file of a sequence for the given ending (e.g., `soft-masked`) and extension (e.g., `bed.gz`).
+fn bed_file_path(output_dir: &Path, file_stem: &str, bed_ending: &str, extension: &str) -> PathBuf {
+    output_dir.join(format!("{}.{}.{}", file_stem, bed_ending, extension))
+}
+
+fn create_bed_writer<W: Write>(
+    output_dir: Option<&Path>,
+    bed_ending: &str,
+    file_stem: &str,
+    extension: &str,
+    append: bool,
+    wrap_file: impl Fn(File) -> W,
+) -> Result<Option<BedWriter<3, BufWriter<W>>>, std::io::Error> {
+    output_dir
+        .map(|output_dir| {
+            let output_path = bed_file_path(output_dir, file_stem, bed_ending, extension);
+            open_output_file(&output_path, append)
+                .map(|file| bed::io::writer::Builder.build_from_writer(wrap_file(file)))
+                .map_err(|error| std::io::Error::new(error.kind(), format!("'{}': {}", output_path.display(), error)))
+        })
+        .transpose()
+}
+
+/// Writes a region in BED format with the given number of columns, i.e., the masking category as name (column 4)
+/// and the region length (capped at 1000) as score (column 5). The line is written directly instead of via
+/// [BedWriter::write_feature_record], as noodles only accepts reference sequence names matching `[[:alnum:]_]{1,255}`
+/// (and would thus reject common names like `NC_000001.1`). The region is given in BED coordinates (0-based start,
+/// exclusive end), so a single base at position `i` is written as `i` to `i + 1`.
+fn write_bed_record<X: std::io::Write, const N: usize>(
+    writer: &mut BedWriter<N, X>,
+    sequence_name: &str,
+    category: &str,
+    start: usize,
+    end: usize,
+    columns: BedColumns,
+) -> Result<(), std::io::Error> {
+    assert!(
+        start < end,
+        "Empty or reversed BED region {}-{} for '{}'. This seems to be a bug.",
+        start,
+        end,
+        sequence_name
+    );
+    let writer = writer.get_mut();
+    match columns {
+        BedColumns::Three => writeln!(writer, "{}\t{}\t{}", sequence_name, start, end)?,
+        BedColumns::Four => writeln!(writer, "{}\t{}\t{}\t{}", sequence_name, start, end, category)?,
+        BedColumns::Five => {
+            let score = (end - start).min(1000);
+            writeln!(writer, "{}\t{}\t{}\t{}\t{}", sequence_name, start, end, category, score)?
+        }
+    }
+    Ok(())
+}
+
+/// Returns the expected GC fraction of a (possibly ambiguous) IUPAC base, or `None` if the base should be excluded
+/// from the GC content calculation (i.e., `N` and non-IUPAC characters).
+pub fn gc_contribution(base: u8) -> Option<f64> {
+    match base.to_ascii_uppercase() {
+        b'C' | b'G' | b'S' => Some(1.0),
+        b'A' | b'T' | b'W' => Some(0.0),
+        b'R' | b'Y' | b'K' | b'M' => Some(0.5),
+        b'B' | b'V' => Some(2.0 / 3.0),
+        b'D' | b'H' => Some(1.0 / 3.0),
+        _ => None,
+    }
+}
+
+/// Extends the current homopolymer run with the (upper case) base, and keeps track of the first longest run.
+fn update_homopolymer_run(
+    base: u8,
+    run_base: &mut u8,
+    run_length: &mut usize,
+    longest_run_base: &mut u8,
+    longest_run_length: &mut usize,
+) {
+    if !matches!(base, b'A' | b'C' | b'G' | b'T') {
+        *run_base = b'.';
+        *run_length = 0;
+        return;
+    }
+    if base == *run_base {
+        *run_length += 1;
+    } else {
+        *run_base = base;
+        *run_length = 1;
+    }
+    if *run_length > *longest_run_length {
+        *longest_run_base = base;
+        *longest_run_length = *run_length;
+    }
+}
+
+/// The canonical nucleotides, in the order used for indexing dinucleotide counts.
+const NUCLEOTIDES: [u8; 4] = *b"ACGT";
+
+/// Index of `CG` in the dinucleotide counts.
+const CPG_INDEX: usize = 4 + 2;
+
+fn dinucleotide_count_map(counts: &[usize; 16]) -> HashMap<String, usize> {
+    counts
+        .iter()
+        .enumerate()
+        .map(|(index, count)| {
+            let dinucleotide = [NUCLEOTIDES[index / 4], NUCLEOTIDES[index % 4]];
+            (String::from_utf8_lossy(&dinucleotide).into_owned(), *count)
+        })
+        .collect()
+}
+
+fn cpg_observed_expected_ratio(cpg_count: usize, a_count: usize, c_count: usize, g_count: usize, t_count: usize) -> f64 {
+    safe_ratio(cpg_count * (a_count + c_count + g_count + t_count), c_count * g_count)
+}
+
+/// Sodium concentration (in mol/l) assumed for the salt-adjusted melting temperature.
+const TM_SODIUM_CONCENTRATION: f64 = 0.05;
+
+/// Estimates the melting temperature (in °C) of an oligo with the given base counts (case-insensitive), using the
+/// Wallace rule `2 * (A + T) + 4 * (G + C)` for sequences shorter than 14 bp and the salt-adjusted formula
+/// `81.5 + 16.6 * log10([Na+]) + 41 * (G + C) / length - 675 / length` (with 50 mM Na+) for longer ones.
+/// Returns `None` for empty sequences and sequences with any other bases (e.g., `N`).
+pub fn melting_temperature(a_count: usize, c_count: usize, g_count: usize, t_count: usize, length: usize) -> Option<f64> {
+    let (at_count, gc_count) = (a_count + t_count, g_count + c_count);
+    if length == 0 || at_count + gc_count != length {
+        return None;
+    }
+    if length < 14 {
+        Some((2 * at_count + 4 * gc_count) as f64)
+    } else {
+        Some(
+            81.5 + 16.6 * TM_SODIUM_CONCENTRATION.log10() + 41.0 * gc_count as f64 / length as f64
+                - 675.0 / length as f64,
+        )
+    }
+}
+
+/// Serializes a map with its keys in sorted order, so that the output does not change between runs.
+fn serialize_sorted<S: serde::Serializer>(
+    map: &Option<HashMap<String, usize>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    map.as_ref()
+        .map(|map| map.iter().collect::<BTreeMap<_, _>>())
+        .serialize(serializer)
+}
+
+/// Returns `num / denom`, or `0.0` if the denominator is zero (instead of NaN or infinity, which JSON cannot represent).
+fn safe_ratio(num: usize, denom: usize) -> f64 {
+    if denom == 0 { 0.0 } else { num as f64 / denom as f64 }
+}
+
+/// Computes the GC content with soft-masked bases (and their `G`/`C`) weighted by the given weight (clamped to
+/// `[0, 1]`), see [SequenceStatistics::gc_content_weighted].
+fn weighted_gc_content(gc_unmasked: usize, gc_soft: usize, unmasked_length: usize, soft_length: usize, weight: f64) -> f64 {
+    let weight = weight.clamp(0.0, 1.0);
+    let length = unmasked_length as f64 + weight * soft_length as f64;
+    if length == 0.0 { 0.0 } else { (gc_unmasked as f64 + weight * gc_soft as f64) / length }
+}
+
+/// Average of two ratios, weighted by their denominators (and `0.0` if both are zero).
+fn weighted_average((first_ratio, first_weight): (f64, usize), (second_ratio, second_weight): (f64, usize)) -> f64 {
+    let total_weight = first_weight + second_weight;
+    if total_weight == 0 {
+        0.0
+    } else {
+        (first_ratio * first_weight as f64 + second_ratio * second_weight as f64) / total_weight as f64
+    }
+}
+
+/// Computes the skew `(x - y) / (x + y)`, or `0.0` if both counts are zero.
+fn skew(x: usize, y: usize) -> f64 {
+    if x + y == 0 { 0.0 } else { (x as f64 - y as f64) / (x + y) as f64 }
+}
+
+/// Computes the Shannon entropy `-sum(p_i * log2(p_i))` (in bits) of the distribution given by the counts.
+fn shannon_entropy(counts: &[usize]) -> f64 {
+    let total: usize = counts.iter().sum();
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / total as f64;
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
+
+/// Copies the FASTA content, replacing each soft-masked base (i.e., `a`, `c`, `g`, or `t`) with `N`. Headers and the
+/// line structure are kept as is.
+pub fn convert_soft_mask_to_n<R: BufRead, W: Write>(mut reader: R, writer: &mut W) -> std::io::Result<()> {
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        if !line.starts_with(b">") {
+            for base in line.iter_mut().filter(|base| is_soft_masked_base(**base)) {
+                *base = b'N';
+            }
+        }
+        writer.write_all(&line)?;
+        line.clear();
+    }
+    Ok(())
+}
+
+/// The characters that count as hard-masked by default, see [StatsOptions::mask_chars].
+pub const DEFAULT_MASK_CHARS: &str = "Nn";
+
+/// Returns a lookup table that tells for each byte whether it is one of the given characters.
+fn mask_char_table(mask_chars: &str) -> [bool; 256] {
+    let mut table = [false; 256];
+    for char in mask_chars.bytes() {
+        table[char as usize] = true;
+    }
+    table
+}
+
+fn is_soft_masked_base(base: u8) -> bool {
+    matches!(base, b'a' | b'c' | b'g' | b't')
+}
+
+fn is_unsupported_iupac_code(base: u8) -> bool {
+    matches!(base, b'b' | b'B' | b'd' | b'D' | b'h' | b'H' | b'k' | b'K' | b'm' | b'M' | b'r' | b'R' | b's' | b'S' | b'v' | b'V' | b'w' | b'W' | b'y' | b'Y')
+}
+
+fn ensure_full_match_regex(regex: &str) -> String {
+    let interior = regex.strip_prefix('^').unwrap_or(regex);
+    let interior = match interior.strip_suffix('$') {
+        Some(stripped) if !ends_with_escape(stripped) => stripped,
+        _ => interior,
+    };
+    // Wrap the pattern into a non-capturing group, so that anchors apply to all alternatives (e.g., 'chr1|chr2').
+    if is_single_group(interior) {
+        format!("^{}$", interior)
+    } else {
+        format!("^(?:{})$", interior)
+    }
+}
+
+/// Checks whether the pattern ends with an unescaped backslash (so a following character would be escaped).
+fn ends_with_escape(pattern: &str) -> bool {
+    pattern.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1
+}
+
+/// Checks whether the whole pattern is a single group, e.g., '(chr1|chr2)' but not '(chr1)|(chr2)'.
+fn is_single_group(pattern: &str) -> bool {
+    if !pattern.starts_with('(') {
+        return false;
+    }
+    let mut depth: usize = 0;
+    let mut chars = pattern.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => {
+                // Skip character classes, in which parentheses have no special meaning.
+                if chars.peek().is_some_and(|(_, c)| *c == '^') {
+                    chars.next();
+                }
+                if chars.peek().is_some_and(|(_, c)| *c == ']') {
+                    chars.next();
+                }
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        ']' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    return index == pattern.len() - 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn ensure_full_match_regex_ok() {
+        assert_eq!(ensure_full_match_regex(".*"), "^(?:.*)$");
+        assert_eq!(ensure_full_match_regex("^.*"), "^(?:.*)$");
+        assert_eq!(ensure_full_match_regex(".*$"), "^(?:.*)$");
+        assert_eq!(ensure_full_match_regex("^.*$"), "^(?:.*)$");
+        assert_eq!(ensure_full_match_regex("abc"), "^(?:abc)$");
+        assert_eq!(ensure_full_match_regex("^abc"), "^(?:abc)$");
+        assert_eq!(ensure_full_match_regex("abc$"), "^(?:abc)$");
+        assert_eq!(ensure_full_match_regex("^abc$"), "^(?:abc)$");
+        assert_eq!(ensure_full_match_regex("abc\\$"), "^(?:abc\\$)$");
+    }
+
+    #[test]
+    fn ensure_full_match_regex_alternations() {
+        assert_eq!(ensure_full_match_regex("chr1|chr2"), "^(?:chr1|chr2)$");
+        assert_eq!(ensure_full_match_regex("^chr1|chr2$"), "^(?:chr1|chr2)$");
+        assert_eq!(ensure_full_match_regex("(chr1)|(chr2)"), "^(?:(chr1)|(chr2))$");
+        assert_eq!(ensure_full_match_regex("(chr1|chr2)"), "^(chr1|chr2)$");
+        assert_eq!(ensure_full_match_regex("^(?:chr1|chr2)$"), "^(?:chr1|chr2)$");
+        assert_eq!(ensure_full_match_regex("([)]|x)"), "^([)]|x)$");
+        assert_eq!(ensure_full_match_regex("(a)[(]|(b)"), "^(?:(a)[(]|(b))$");
+
+        let regex = Regex::new(&ensure_full_match_regex("chr1|chr2")).unwrap();
+        assert!(regex.is_match("chr1"));
+        assert!(regex.is_match("chr2"));
+        assert!(!regex.is_match("chr10"));
+        assert!(!regex.is_match("chr1_random"));
+        assert!(!regex.is_match("xchr2"));
+    }
+
+    #[test]
+    fn compute_assembly_statistics_ok() {
+        let stats: Vec<SequenceStatistics> = [2, 9, 3, 4, 8, 2]
+            .iter()
+            .enumerate()
+            .map(|(i, length)| {
+                let record = FastaRecord::new(
+                    noodles_fasta::record::Definition::new(format!("seq{}", i), None),
+                    noodles_fasta::record::Sequence::from(vec![b'A'; *length]),
+                );
+                process_fasta_record(&record, 1, None, &StatsOptions::default()).unwrap().unwrap()
+            })
+            .collect();
+
+        let assembly_stats = compute_assembly_statistics(&stats);
+        assert_eq!(assembly_stats.total_length, 28);
+        assert_eq!(assembly_stats.num_sequences, 6);
+        assert_eq!(assembly_stats.n50, 8);
+        assert_eq!(assembly_stats.l50, 2);
+        assert_eq!(assembly_stats.longest, 9);
+        assert_eq!(assembly_stats.shortest, 2);
+        assert_eq!(assembly_stats.mean_length, 28.0 / 6.0);
+        // The middle lengths of [9, 8, 4, 3, 2, 2] are 4 and 3.
+        assert_eq!(assembly_stats.median_length, 3.5);
+        assert_eq!(assembly_stats.top_n_fraction, vec![(1, 9.0 / 28.0), (5, 26.0 / 28.0)]);
+        assert_eq!(assembly_stats.nx_curve.len(), 101);
+        assert_eq!(assembly_stats.nx_curve[0], (0, 9));
+        assert_eq!(assembly_stats.nx_curve[30], (30, 9));
+        assert_eq!(assembly_stats.nx_curve[33], (33, 8));
+        assert_eq!(assembly_stats.nx_curve[50], (50, assembly_stats.n50));
+        assert_eq!(assembly_stats.nx_curve[70], (70, 4));
+        assert_eq!(assembly_stats.nx_curve[100], (100, 2));
+
+        let odd_stats = compute_assembly_statistics(&stats[..5]);
+        // The middle length of [9, 8, 4, 3, 2] is 4.
+        assert_eq!(odd_stats.median_length, 4.0);
+        assert_eq!(odd_stats.mean_length, 26.0 / 5.0);
+
+        let empty_stats = compute_assembly_statistics(&[]);
+        assert_eq!(empty_stats.total_length, 0);
+        assert_eq!(empty_stats.n50, 0);
+        assert_eq!(empty_stats.l50, 0);
+        assert_eq!(empty_stats.mean_length, 0.0);
+        assert_eq!(empty_stats.median_length, 0.0);
+        assert!(empty_stats.nx_curve.iter().all(|(_, nx)| *nx == 0));
+        assert!(empty_stats.top_n_fraction.is_empty());
+    }
+
+    #[test]
+    fn convert_soft_mask_to_n_ok() -> Result<(), Box<dyn Error>> {
+        let fasta_content = b">seq1 soft-masked\nACGTacgt\nnnNNacRy\n>seq2\ntttt\nTT\n";
+        let mut converted = Vec::new();
+        convert_soft_mask_to_n(&fasta_content[..], &mut converted)?;
+        assert_eq!(converted, b">seq1 soft-masked\nACGTNNNN\nnnNNNNRy\n>seq2\nNNNN\nTT\n");
+
+        let mut reader = fasta::io::Reader::new(&converted[..]);
+        let records: Vec<FastaRecord> = reader.records().collect::<Result<_, _>>()?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].description(), Some(b"soft-masked".as_bstr()));
+        assert_eq!(records[0].sequence().as_ref(), b"ACGTNNNNnnNNNNRy");
+        assert_eq!(records[1].sequence().as_ref(), b"NNNNTT");
+        Ok(())
+    }
+
+    #[test]
+    fn group_assembly_statistics_ok() -> Result<(), Box<dyn Error>> {
+        let names_and_lengths = [("sample1_contig1", 4), ("sample2_contig1", 3), ("sample1_contig2", 2), ("chrM", 5)];
+        let stats: Vec<SequenceStatistics> = names_and_lengths
+            .iter()
+            .map(|(name, length)| {
+                let record = FastaRecord::new(
+                    noodles_fasta::record::Definition::new(*name, None),
+                    noodles_fasta::record::Sequence::from(vec![b'A'; *length]),
+                );
+                process_fasta_record(&record, 1, None, &StatsOptions::default()).unwrap().unwrap()
+            })
+            .collect();
+
+        let groups = group_assembly_statistics(&stats, "(sample\\d+)_.*")?;
+        assert_eq!(groups.keys().collect::<Vec<_>>(), vec!["sample1", "sample2", UNGROUPED_NAME]);
+        assert_eq!(groups["sample1"].num_sequences, 2);
+        assert_eq!(groups["sample1"].total_length, 6);
+        assert_eq!(groups["sample1"].longest, 4);
+        assert_eq!(groups["sample2"].total_length, 3);
+        assert_eq!(groups[UNGROUPED_NAME].total_length, 5);
+
+        assert!(group_assembly_statistics(&[], "(").is_err());
+        assert_eq!(
+            group_assembly_statistics(&stats, "sample.*").unwrap_err(),
+            ProcessError::InvalidRegex("sample.*".to_string())
+        );
+        assert!(group_assembly_statistics(&stats, "(sample\\d+)_(.*)").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_ok() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(
+                b"CTGTGCTGGCATAGTGGTCTCACCTCCGGCAGtatcaccaccactgggcacaagcttctccagcacagcaNNnNnactgtgtcttatttctccttgtactcccagtgttcacaccatgctgcactcacagaagactcttcgttgatattt".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let stats = process_fasta_record(&record, 1, None, &options)?;
+        assert!(stats.is_some());
+        let stats = stats.unwrap();
+
+        // Check the output BED files
+        let non_masked_bed_path = tmpdir.path().join("test_sequence.non-masked.bed");
+        let non_masked_bed = std::fs::read_to_string(non_masked_bed_path)?;
+        assert_eq!(non_masked_bed, "test_sequence\t0\t32\n");
+        
+        let soft_masked_bed_path = tmpdir.path().join("test_sequence.soft-masked.bed");
+        let soft_masked_bed = std::fs::read_to_string(soft_masked_bed_path)?;
+        assert_eq!(soft_masked_bed, "test_sequence\t32\t70\ntest_sequence\t75\t150\n");
+
+        let hard_masked_bed_path = tmpdir.path().join("test_sequence.hard-masked.bed");
+        let hard_masked_bed = std::fs::read_to_string(hard_masked_bed_path)?;
+        assert_eq!(hard_masked_bed, "test_sequence\t70\t75\n");
+
+        assert_eq!(stats.sequence_name, "test_sequence");
+        assert_eq!(stats.non_masked_bases, 32);
+        assert_eq!(stats.non_masked_ratio, 32.0 / 150.0);
+
+        assert_eq!(stats.soft_masked_bases, 113);
+        assert_eq!(stats.soft_masked_ratio, 113.0 / 150.0);
+
+        assert_eq!(stats.hard_masked_bases, 5);
+        assert_eq!(stats.hard_masked_ratio, 5.0 / 150.0);
+        
+        assert_eq!(stats.gc_content, 73.0 / 150.0);
+        assert_eq!(stats.gc_content_no_n, 73.0 / 145.0);
+        assert_eq!(stats.gc_content_masked, 53.0 / 118.0);
+        assert_eq!(stats.gc_content_unmasked, 20.0 / 32.0);
+        assert_eq!(stats.at_content, 72.0 / 150.0);
+        assert_eq!(stats.gc_skew, skew(27, 46));
+        assert_eq!(stats.at_skew, skew(30, 42));
+        assert_eq!(stats.iupac_ambiguous_bases, 0);
+        assert_eq!(stats.other_bases, 0);
+        assert_eq!(stats.a_count, 30);
+        assert_eq!(stats.c_count, 46);
+        assert_eq!(stats.g_count, 27);
+        assert_eq!(stats.t_count, 42);
+        assert_eq!(stats.n_count, 5);
+        assert_eq!(stats.sequence_length, 150);
+        assert_eq!(stats.checksum_sha256, "39d1aba0a51cb46ce7cef81ac808bf46e3594b0ccabcd77fabf19c2a395651fa");
+        Ok(())
+    }
+    
+
+    #[test]
+    fn process_fasta_record_combined_bed_ok() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGTacgtNNacACGT".to_vec()),
+        );
+
+        let combined_bed_output = Mutex::new(Vec::new());
+        process_fasta_record(&record, 1, Some(&combined_bed_output), &StatsOptions::default())?;
+        process_fasta_record(&record, 1, Some(&combined_bed_output), &StatsOptions::default())?;
+
+        let record_regions = "test_sequence\t0\t4\tnon-masked\n\
+            test_sequence\t4\t8\tsoft-masked\n\
+            test_sequence\t8\t10\thard-masked\n\
+            test_sequence\t10\t12\tsoft-masked\n\
+            test_sequence\t12\t16\tnon-masked\n";
+        assert_eq!(
+            String::from_utf8(combined_bed_output.into_inner()?)?,
+            record_regions.repeat(2)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_bed_columns() -> Result<(), Box<dyn Error>> {
+        let mut sequence = b"ACGT".to_vec();
+        sequence.extend(b"N".repeat(1200));
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(sequence),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            bed_columns: BedColumns::Five,
+            ..Default::default()
+        };
+        process_fasta_record(&record, 1, None, &options)?;
+        assert_eq!(
+            std::fs::read_to_string(tmpdir.path().join("test_sequence.non-masked.bed"))?,
+            "test_sequence\t0\t4\tnon-masked\t4\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(tmpdir.path().join("test_sequence.hard-masked.bed"))?,
+            "test_sequence\t4\t1204\thard-masked\t1000\n"
+        );
+
+        let combined_bed_output = Mutex::new(Vec::new());
+        process_fasta_record(&record, 1, Some(&combined_bed_output), &options)?;
+        assert_eq!(
+            String::from_utf8(combined_bed_output.into_inner()?)?,
+            "test_sequence\t0\t4\tnon-masked\t4\ntest_sequence\t4\t1204\thard-masked\t1000\n"
+        );
+
+        // A zero-length buffer is full right away, so writing the combined BED output fails.
+        let combined_bed_output = Mutex::new(std::io::Cursor::new([0u8; 0]));
+        let error = process_fasta_record(&record, 1, Some(&combined_bed_output), &options).unwrap_err();
+        assert!(matches!(&error, ProcessError::WriteError(message) if message.contains("combined output BED")));
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_bed_dir() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGTacgtNN".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let bed_dir = tmpdir.path().join("bed");
+        std::fs::create_dir(&bed_dir)?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            bed_dir: Some(bed_dir.clone()),
+            gc_window: Some(5),
+            ..Default::default()
+        };
+        process_fasta_record(&record, 1, None, &options)?;
+        for bed_ending in ["non-masked", "soft-masked", "hard-masked"] {
+            assert!(bed_dir.join(format!("test_sequence.{}.bed", bed_ending)).exists());
+            assert!(!tmpdir.path().join(format!("test_sequence.{}.bed", bed_ending)).exists());
+        }
+        // Other per-sequence files are still written to the output directory.
+        assert!(tmpdir.path().join("test_sequence.gc.bedgraph").exists());
+        assert!(!bed_dir.join("test_sequence.gc.bedgraph").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_single_base_regions() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions { output_dir: Some(tmpdir.path().to_path_buf()), ..Default::default() };
+        let read_bed = |category: &str| std::fs::read_to_string(tmpdir.path().join(format!("seq.{}.bed", category)));
+        let process = |sequence: &[u8]| {
+            let record = FastaRecord::new(
+                noodles_fasta::record::Definition::new("seq", None),
+                noodles_fasta::record::Sequence::from(sequence.to_vec()),
+            );
+            process_fasta_record(&record, 1, None, &options)
+        };
+
+        // Single-base regions at both ends of the sequence.
+        process(b"AccccT")?;
+        assert_eq!(read_bed("non-masked")?, "seq\t0\t1\nseq\t5\t6\n");
+        assert_eq!(read_bed("soft-masked")?, "seq\t1\t5\n");
+
+        process(b"aCCCCn")?;
+        assert_eq!(read_bed("soft-masked")?, "seq\t0\t1\n");
+        assert_eq!(read_bed("non-masked")?, "seq\t1\t5\n");
+        assert_eq!(read_bed("hard-masked")?, "seq\t5\t6\n");
+
+        // A sequence that consists of a single base, and single-base regions that alternate.
+        process(b"N")?;
+        assert_eq!(read_bed("hard-masked")?, "seq\t0\t1\n");
+        process(b"AcN")?;
+        assert_eq!(read_bed("non-masked")?, "seq\t0\t1\n");
+        assert_eq!(read_bed("soft-masked")?, "seq\t1\t2\n");
+        assert_eq!(read_bed("hard-masked")?, "seq\t2\t3\n");
+
+        // Single-base regions at chunk boundaries.
+        let regions = scan_sequence("seq", b"AcNc", 1, &StatsOptions::default())?.regions;
+        let intervals: Vec<(usize, usize)> = regions.iter().map(|region| (region.start, region.end)).collect();
+        assert_eq!(intervals, [(0, 1), (1, 2), (2, 3), (3, 4)]);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_mask_track() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACgtNNACGTnAC".to_vec()),
+        );
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions { output_dir: Some(tmpdir.path().to_path_buf()), mask_track: true, ..Default::default() };
+        process_fasta_record(&record, 1, None, &options)?;
+
+        let mask_track_path = tmpdir.path().join("test_sequence.mask.bin");
+        // The header (magic bytes and length) is followed by two bytes for the 13 bases.
+        assert_eq!(std::fs::metadata(&mask_track_path)?.len(), 4 + 8 + 2);
+        let mask = read_mask_track(&mask_track_path)?;
+        let expected: Vec<bool> = b"ACgtNNACGTnAC".iter().map(|base| !base.is_ascii_uppercase() || *base == b'N').collect();
+        assert_eq!(mask.iter().collect::<Vec<_>>(), expected);
+
+        std::fs::write(&mask_track_path, b"FMSK\x10\0\0\0\0\0\0\0\xff")?;
+        assert_eq!(read_mask_track(&mask_track_path).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        std::fs::write(&mask_track_path, b"ACGT")?;
+        assert!(read_mask_track(&mask_track_path).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_merge_mask_bed() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGTacgtNNNacGTnnRaaCG".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            merge_mask_bed: true,
+            ignore_iupac: true,
+            ..Default::default()
+        };
+        process_fasta_record(&record, 1, None, &options)?;
+        assert_eq!(
+            std::fs::read_to_string(tmpdir.path().join("test_sequence.masked.bed"))?,
+            "test_sequence\t4\t13\ntest_sequence\t15\t17\ntest_sequence\t18\t20\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(tmpdir.path().join("test_sequence.hard-masked.bed"))?,
+            "test_sequence\t8\t11\ntest_sequence\t15\t17\n"
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions { output_dir: Some(tmpdir.path().to_path_buf()), ..Default::default() };
+        process_fasta_record(&record, 1, None, &StatsOptions { ignore_iupac: true, ..options })?;
+        assert!(!tmpdir.path().join("test_sequence.masked.bed").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_compress_bed() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGTacgtNNacACGT".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            compress_bed: true,
+            ..Default::default()
+        };
+        process_fasta_record(&record, 1, None, &options)?;
+        assert!(!tmpdir.path().join("test_sequence.soft-masked.bed").exists());
+
+        let mut soft_masked_bed = String::new();
+        let file = File::open(tmpdir.path().join("test_sequence.soft-masked.bed.gz"))?;
+        flate2::read::MultiGzDecoder::new(file).read_to_string(&mut soft_masked_bed)?;
+        assert_eq!(soft_masked_bed, "test_sequence\t4\t8\ntest_sequence\t10\t12\n");
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_length_range() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            min_length: Some(4),
+            max_length: Some(5),
+            ..Default::default()
+        };
+        let mut stats = Vec::new();
+        for length in 3..=6 {
+            let record = FastaRecord::new(
+                noodles_fasta::record::Definition::new(format!("seq{}", length), None),
+                noodles_fasta::record::Sequence::from(b"A".repeat(length)),
+            );
+            stats.extend(process_fasta_record(&record, 1, None, &options)?);
+        }
+
+        let names: Vec<&str> = stats.iter().map(|s| s.sequence_name.as_str()).collect();
+        assert_eq!(names, vec!["seq4", "seq5"]);
+        // Filtered records do not write any files and are not part of the assembly statistics.
+        assert!(!tmpdir.path().join("seq3.non-masked.bed").exists());
+        assert!(!tmpdir.path().join("seq6.non-masked.bed").exists());
+        let assembly_stats = compute_assembly_statistics(&stats);
+        assert_eq!(assembly_stats.num_sequences, 2);
+        assert_eq!(assembly_stats.total_length, 9);
+        assert_eq!(assembly_stats.shortest, 4);
+        assert_eq!(assembly_stats.longest, 5);
+
+        // The length filter is applied only to records matching the regex.
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("other", None),
+            noodles_fasta::record::Sequence::from(b"A".repeat(4)),
+        );
+        let options = StatsOptions { match_regex: Some("seq.*".to_string()), ..options };
+        assert!(process_fasta_record(&record, 1, None, &options)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_case_insensitive_names() -> Result<(), Box<dyn Error>> {
+        let options = StatsOptions {
+            match_regex: Some(r"CHR\d+".to_string()),
+            exclude_regex: Some("chrm".to_string()),
+            ..Default::default()
+        };
+        let process = |name: &str, options: &StatsOptions| {
+            let record = FastaRecord::new(
+                noodles_fasta::record::Definition::new(name, None),
+                noodles_fasta::record::Sequence::from(b"ACGT".to_vec()),
+            );
+            process_fasta_record(&record, 1, None, options)
+        };
+        assert!(process("chr1", &options)?.is_none());
+        assert!(process("ChrM", &options)?.is_none());
+
+        let options = StatsOptions { case_insensitive_names: true, ..options };
+        assert!(process("chr1", &options)?.is_some());
+        assert!(process("Chr22", &options)?.is_some());
+        assert!(process("chrX", &options)?.is_none());
+        let options = StatsOptions { match_regex: Some("chr.*".to_string()), ..options };
+        assert!(process("ChrM", &options)?.is_none());
+        assert!(process("CHRX", &options)?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_exclude_regex() -> Result<(), Box<dyn Error>> {
+        let options = StatsOptions {
+            match_regex: Some("chr.*".to_string()),
+            exclude_regex: Some("chrUn_.*|.*_random".to_string()),
+            ..Default::default()
+        };
+        let process = |name: &str| {
+            let record = FastaRecord::new(
+                noodles_fasta::record::Definition::new(name, None),
+                noodles_fasta::record::Sequence::from(b"ACGT".to_vec()),
+            );
+            process_fasta_record(&record, 1, None, &options)
+        };
+
+        assert!(process("chr1")?.is_some());
+        // Matches both patterns, so it is excluded.
+        assert!(process("chrUn_KI270302v1")?.is_none());
+        assert!(process("chr1_KI270706v1_random")?.is_none());
+        // Full-match semantics: a partial match is not excluded.
+        assert!(process("chr1_random_extra")?.is_some());
+        // Names are matched case-sensitively by default.
+        assert!(process("Chr1")?.is_none());
+
+        let invalid_options = StatsOptions { exclude_regex: Some("(".to_string()), ..Default::default() };
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("chr1", None),
+            noodles_fasta::record::Sequence::from(b"ACGT".to_vec()),
+        );
+        assert_eq!(
+            process_fasta_record(&record, 1, None, &invalid_options).unwrap_err(),
+            ProcessError::InvalidRegex("(".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_file_prefix() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("chr1", None),
+            noodles_fasta::record::Sequence::from(b"ACGTacgtNN".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            file_prefix: "mygenome".to_string(),
+            gc_window: Some(5),
+            ..Default::default()
+        };
+        process_fasta_record(&record, 1, None, &options)?;
+        assert!(tmpdir.path().join("mygenome.chr1.non-masked.bed").exists());
+        assert!(tmpdir.path().join("mygenome.chr1.soft-masked.bed").exists());
+        assert!(tmpdir.path().join("mygenome.chr1.hard-masked.bed").exists());
+        assert!(tmpdir.path().join("mygenome.chr1.gc.bedgraph").exists());
+        assert!(!tmpdir.path().join("chr1.non-masked.bed").exists());
+
+        assert_eq!(prefixed_file_name("", "summary.json"), "summary.json");
+        assert_eq!(prefixed_file_name("mygenome", "summary.json"), "mygenome.summary.json");
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_unwritable_output() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("chr1", None),
+            noodles_fasta::record::Sequence::from(b"ACGTNN".to_vec()),
+        );
+
+        // A file in place of the output directory cannot be written into, unlike a read-only directory when run as root.
+        let tmpdir = tempfile::tempdir()?;
+        let output_dir = tmpdir.path().join("not-a-directory");
+        std::fs::write(&output_dir, "")?;
+        let options = StatsOptions {
+            output_dir: Some(output_dir.clone()),
+            ..Default::default()
+        };
+        let error = process_fasta_record(&record, 1, None, &options).unwrap_err();
+        assert!(matches!(&error, ProcessError::WriteError(message) if message.contains("chr1.non-masked.bed")));
+
+        // Without BED output, nothing needs to be written.
+        let options = StatsOptions { output_dir: Some(output_dir.clone()), no_bed_output: true, ..Default::default() };
+        assert!(process_fasta_record(&record, 1, None, &options)?.is_some());
+
+        // A directory in place of the GC profile file cannot be opened for writing.
+        let output_dir = tmpdir.path().join("output");
+        std::fs::create_dir_all(output_dir.join("chr1.gc_profile.tsv"))?;
+        let options = StatsOptions {
+            output_dir: Some(output_dir.clone()),
+            no_bed_output: true,
+            gc_bins: Some(2),
+            ..Default::default()
+        };
+        let error = process_fasta_record(&record, 1, None, &options).unwrap_err();
+        assert!(matches!(&error, ProcessError::WriteError(message) if message.contains("chr1.gc_profile.tsv")));
+
+        std::fs::create_dir_all(output_dir.join("chr1.gc.bedgraph"))?;
+        let options = StatsOptions {
+            output_dir: Some(output_dir.clone()),
+            no_bed_output: true,
+            gc_window: Some(2),
+            ..Default::default()
+        };
+        let error = process_fasta_record(&record, 1, None, &options).unwrap_err();
+        assert!(matches!(&error, ProcessError::WriteError(message) if message.contains("chr1.gc.bedgraph")));
+
+        std::fs::create_dir_all(output_dir.join("chr1.kmers.tsv"))?;
+        let options = StatsOptions {
+            output_dir: Some(output_dir.clone()),
+            no_bed_output: true,
+            kmer_size: Some(2),
+            ..Default::default()
+        };
+        let error = process_fasta_record(&record, 1, None, &options).unwrap_err();
+        assert!(matches!(&error, ProcessError::WriteError(message) if message.contains("chr1.kmers.tsv")));
+        Ok(())
+    }
+
+    // Writing to `/dev/full` fails with "No space left on device", like on a full disk.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn process_fasta_record_full_disk() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("chr1", None),
+            noodles_fasta::record::Sequence::from(b"ACGTNN".to_vec()),
+        );
+
+        for (compress_bed, bed_file_name) in [(false, "chr1.hard-masked.bed"), (true, "chr1.hard-masked.bed.gz")] {
+            let tmpdir = tempfile::tempdir()?;
+            std::os::unix::fs::symlink("/dev/full", tmpdir.path().join(bed_file_name))?;
+            let options = StatsOptions {
+                output_dir: Some(tmpdir.path().to_path_buf()),
+                compress_bed,
+                ..Default::default()
+            };
+            let error = process_fasta_record(&record, 1, None, &options).unwrap_err();
+            assert!(matches!(&error, ProcessError::WriteError(message) if message.contains(bed_file_name)));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_no_soft_mask() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"acgtacgtnn".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            no_soft_mask: true,
+            ..Default::default()
+        };
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        assert_eq!(stats.soft_masked_bases, 0);
+        assert_eq!(stats.soft_masked_ratio, 0.0);
+        assert_eq!(stats.non_masked_bases, 8);
+        assert_eq!(stats.hard_masked_bases, 2);
+        assert_eq!(stats.gc_content, 0.4);
+        assert_eq!(std::fs::read_to_string(tmpdir.path().join("test_sequence.soft-masked.bed"))?, "");
+        assert_eq!(
+            std::fs::read_to_string(tmpdir.path().join("test_sequence.non-masked.bed"))?,
+            "test_sequence\t0\t8\n"
+        );
+
+        let stats = process_fasta_record(&record, 1, None, &StatsOptions::default())?.unwrap();
+        assert_eq!(stats.soft_masked_bases, 8);
+        assert_eq!(stats.non_masked_bases, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_split_n_case() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACnnNNGTnNac".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        assert_eq!(stats.soft_masked_bases, 2);
+        assert_eq!(stats.hard_masked_bases, 6);
+        assert_eq!(stats.gap_lengths, vec![4, 2]);
+        assert_eq!(
+            std::fs::read_to_string(tmpdir.path().join("test_sequence.soft-masked.bed"))?,
+            "test_sequence\t10\t12\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(tmpdir.path().join("test_sequence.hard-masked.bed"))?,
+            "test_sequence\t2\t6\ntest_sequence\t8\t10\n"
+        );
+
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            split_n_case: true,
+            ..Default::default()
+        };
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        assert_eq!(stats.non_masked_bases, 4);
+        assert_eq!(stats.soft_masked_bases, 5);
+        assert_eq!(stats.hard_masked_bases, 3);
+        assert_eq!(stats.n_count, 6);
+        assert_eq!(stats.gap_lengths, vec![2, 1]);
+        assert_eq!(stats.gc_content_no_n, 0.5);
+        assert_eq!(
+            std::fs::read_to_string(tmpdir.path().join("test_sequence.soft-masked.bed"))?,
+            "test_sequence\t2\t4\ntest_sequence\t8\t9\ntest_sequence\t10\t12\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(tmpdir.path().join("test_sequence.hard-masked.bed"))?,
+            "test_sequence\t4\t6\ntest_sequence\t9\t10\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_region_counts() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"AAAAnnnnAAAA".to_vec()),
+        );
+        // Regions are counted even without BED output.
+        let stats = process_fasta_record(&record, 1, None, &StatsOptions::default())?.unwrap();
+        assert_eq!(stats.non_masked_region_count, 2);
+        assert_eq!(stats.soft_masked_region_count, 0);
+        assert_eq!(stats.hard_masked_region_count, 1);
+
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"acACgtNNaNGT".to_vec()),
+        );
+        let stats = process_fasta_record(&record, 1, None, &StatsOptions::default())?.unwrap();
+        assert_eq!(stats.non_masked_region_count, 2);
+        assert_eq!(stats.soft_masked_region_count, 3);
+        assert_eq!(stats.hard_masked_region_count, 2);
+        assert_eq!(stats.soft_mask_region_mean_length, 5.0 / 3.0);
+        assert_eq!(stats.soft_mask_region_max_length, 2);
+        assert_eq!(stats.soft_mask_region_lengths, None);
+
+        let options = StatsOptions { soft_mask_region_lengths: true, ..Default::default() };
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        assert_eq!(stats.soft_mask_region_lengths, Some(vec![2, 2, 1]));
+        let merged = merge_sequence_statistics(&stats, &compute_statistics("second", b"ACaaaaGG", &options)?);
+        assert_eq!(merged.soft_mask_region_lengths, Some(vec![2, 2, 1, 4]));
+        assert_eq!(merged.soft_mask_region_mean_length, 9.0 / 4.0);
+        assert_eq!(merged.soft_mask_region_max_length, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn scan_sequence_chunks() -> Result<(), Box<dyn Error>> {
+        // Pseudo-random sequence with long runs, so that runs and regions span chunk boundaries.
+        let alphabet = b"AAAACCGGTTTTaaccggttNNNNnnRy";
+        let mut sequence = b"GGGGGG".to_vec();
+        let mut state: u64 = 42;
+        while sequence.len() < 5000 {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            let base = alphabet[(state >> 33) as usize % alphabet.len()];
+            sequence.extend(std::iter::repeat_n(base, 1 + (state >> 50) as usize % 12));
+        }
+
+        let options = StatsOptions {
+            ignore_iupac: true,
+            dinucleotides: true,
+            mask_window: Some(10),
+            gc_skew_window: Some(7),
+            report_unexpected: true,
+            ..Default::default()
+        };
+        let serial = scan_sequence("test_sequence", &sequence, sequence.len(), &options)?;
+        assert!(serial.regions.len() > 100);
+        assert_eq!(serial.unexpected_bases.len(), serial.iupac_ambiguous_bases_counter);
+        assert_eq!(serial.masked_window_counts.len(), sequence.len().div_ceil(10));
+        assert_eq!(serial.gc_skew_window_counts.len(), sequence.len().div_ceil(7));
+        for chunk_size in [1, 2, 3, 7, 64, 1000, 4999] {
+            assert_eq!(scan_sequence("test_sequence", &sequence, chunk_size, &options)?, serial, "chunk size {}", chunk_size);
+        }
+
+        let options = StatsOptions::default();
+        assert_eq!(
+            scan_sequence("test_sequence", b"ACGTACGTAR", 3, &options).unwrap_err(),
+            ProcessError::UnexpectedBase { base: b'R', sequence: "test_sequence".to_string() }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_checksum_only() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGTacgtNNxy".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            checksum_only: true,
+            checksum_kind: ChecksumKind::Both,
+            gc_window: Some(5),
+            ..Default::default()
+        };
+        // Unexpected bases are not detected, as the bases are not classified at all.
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        assert_eq!(stats.sequence_length, 12);
+        assert_eq!(stats.checksum_sha256, format!("{:x}", Sha256::digest(b"ACGTacgtNNxy")));
+        assert_eq!(stats.checksum_md5, Some(format!("{:x}", Md5::digest(b"ACGTacgtNNxy"))));
+        assert_eq!(stats.non_masked_bases, 0);
+        assert_eq!(stats.gc_content, 0.0);
+        assert_eq!(std::fs::read_dir(tmpdir.path())?.count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_strict() -> Result<(), Box<dyn Error>> {
+        let options = StatsOptions { strict: true, ..Default::default() };
+        let process = |sequence: &[u8], options: &StatsOptions| {
+            let record = FastaRecord::new(
+                noodles_fasta::record::Definition::new("test_sequence", None),
+                noodles_fasta::record::Sequence::from(sequence.to_vec()),
+            );
+            process_fasta_record(&record, 1, None, options)
+        };
+
+        assert_eq!(process(b"ACGTNN", &options)?.unwrap().non_masked_bases, 4);
+        let expected_error = ProcessError::UnexpectedSoftMask { position: 5, sequence: "test_sequence".to_string() };
+        assert_eq!(process(b"ACGTNnacgt", &options).unwrap_err(), expected_error);
+        // Lowercase bases are rejected even if they are not counted as soft-masked, or not classified at all.
+        let no_soft_mask_options = StatsOptions { no_soft_mask: true, ..options.clone() };
+        assert_eq!(process(b"ACGTNnacgt", &no_soft_mask_options).unwrap_err(), expected_error);
+        let checksum_only_options = StatsOptions { checksum_only: true, ..options.clone() };
+        assert_eq!(process(b"ACGTNnacgt", &checksum_only_options).unwrap_err(), expected_error);
+        assert!(process(b"acgt", &StatsOptions::default())?.is_some());
+
+        // The first offending position is reported even if the sequence is scanned in chunks.
+        let mut sequence = b"A".repeat(100);
+        sequence[37] = b'c';
+        sequence[75] = b'g';
+        for chunk_size in [1, 10, 40, 100] {
+            assert_eq!(
+                scan_sequence("test_sequence", &sequence, chunk_size, &options).unwrap_err(),
+                ProcessError::UnexpectedSoftMask { position: 37, sequence: "test_sequence".to_string() }
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_mask_chars() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("mask_chars_test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACXXgtNNnxAC".to_vec()),
+        );
+        assert_eq!(
+            process_fasta_record(&record, 1, None, &StatsOptions::default()).unwrap_err(),
+            ProcessError::UnexpectedBase { base: b'X', sequence: "mask_chars_test_sequence".to_string() }
+        );
+
+        let options = StatsOptions { mask_chars: Some("NnXx".to_string()), ..Default::default() };
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        assert_eq!(stats.non_masked_bases, 4);
+        assert_eq!(stats.soft_masked_bases, 2);
+        assert_eq!(stats.hard_masked_bases, 6);
+        assert_eq!(stats.n_count, 6);
+        assert_eq!(stats.gap_lengths, vec![2, 4]);
+
+        // Characters that are not in the set are unexpected, even `N`.
+        let options = StatsOptions { mask_chars: Some("Xx".to_string()), ignore_iupac: true, ..Default::default() };
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        assert_eq!(stats.hard_masked_bases, 3);
+        assert_eq!(stats.n_count, 3);
+        assert_eq!(stats.other_bases, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_description() -> Result<(), Box<dyn Error>> {
+        let fasta_content = b">NC_000001.11 Homo sapiens chromosome 1\nACGT\n>chrM\nAC\n";
+        let mut reader = fasta::io::Reader::new(&fasta_content[..]);
+        let records: Vec<FastaRecord> = reader.records().collect::<Result<_, _>>()?;
+        let options = StatsOptions::default();
+        let process_record = process_fasta(None, &options);
+
+        let stats = process_record(&records[0], 1)?.remove(0);
+        assert_eq!(stats.sequence_name, "NC_000001.11");
+        assert_eq!(stats.description.as_deref(), Some("Homo sapiens chromosome 1"));
+        assert!(serde_json::to_string(&stats)?.contains("\"description\":\"Homo sapiens chromosome 1\""));
+
+        let stats = process_record(&records[1], 1)?.remove(0);
+        assert_eq!(stats.description, None);
+        assert!(!serde_json::to_string(&stats)?.contains("description"));
+        Ok(())
+    }
+
+    #[test]
+    fn position_frequency_matrix_ok() -> Result<(), Box<dyn Error>> {
+        let matrix = Arc::new(Mutex::new(PositionFrequencyMatrix::default()));
+        assert_eq!(matrix.lock().unwrap().counts(), &[] as &[[usize; 4]]);
+        let options = StatsOptions { position_frequency_matrix: Some(matrix.clone()), ..Default::default() };
+        compute_statistics("oligo1", b"ACGT", &options)?;
+        compute_statistics("oligo2", b"acgA", &options)?;
+        compute_statistics("oligo3", b"TNGA", &options)?;
+        assert_eq!(matrix.lock().unwrap().counts(), [[2, 0, 0, 1], [0, 2, 0, 0], [0, 0, 3, 0], [2, 0, 0, 1]]);
+
+        // Sequences of another length are not added.
+        assert_eq!(
+            compute_statistics("oligo4", b"ACG", &options).unwrap_err(),
+            ProcessError::UnequalLengths { sequence: "oligo4".to_string(), length: 3, expected_length: 4 }
+        );
+        assert_eq!(matrix.lock().unwrap().counts(), [[2, 0, 0, 1], [0, 2, 0, 0], [0, 0, 3, 0], [2, 0, 0, 1]]);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_timings() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGTacgtNN".to_vec()),
+        );
+        let stats = process_fasta(None, &StatsOptions::default())(&record, 1)?.remove(0);
+        assert_eq!(stats.processing_ms, None);
+        assert!(!serde_json::to_string(&stats)?.contains("processing_ms"));
+
+        let options = StatsOptions { record_timings: true, ..Default::default() };
+        let stats = process_fasta(None, &options)(&record, 1)?.remove(0);
+        assert!(stats.processing_ms.is_some_and(|processing_ms| processing_ms >= 0.0));
+        let merged = merge_sequence_statistics(&stats, &stats);
+        assert_eq!(merged.processing_ms, stats.processing_ms.map(|processing_ms| 2.0 * processing_ms));
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_regions() -> Result<(), Box<dyn Error>> {
+        let bed_content = b"seq1\t6\t10\nseq1\t0\t4\nseq1\t2\t5\nseq2\t100\t200\nseq1\t10\t99\n";
+        let regions = read_regions(&bed_content[..])?;
+        assert_eq!(regions["seq1"], vec![(0, 4), (2, 5), (6, 10), (10, 99)]);
+        assert_eq!(regions["seq2"], vec![(100, 200)]);
+
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("seq1", None),
+            noodles_fasta::record::Sequence::from(b"ACGTacNNNNGG".to_vec()),
+        );
+        let options = StatsOptions { regions: Some(Arc::new(regions)), ..Default::default() };
+        let stats = process_fasta(None, &options)(&record, 1)?;
+        // Overlapping and adjacent regions are merged, and the last one is clipped to the sequence.
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].sequence_name, "seq1");
+        assert_eq!(stats[0].sequence_length, 11);
+        assert_eq!(stats[0].non_masked_bases, 6);
+        assert_eq!(stats[0].soft_masked_bases, 1);
+        assert_eq!(stats[0].hard_masked_bases, 4);
+
+        let per_region_options = StatsOptions { per_region: true, ..options.clone() };
+        let stats = process_fasta(None, &per_region_options)(&record, 1)?;
+        let names: Vec<&str> = stats.iter().map(|stats| stats.sequence_name.as_str()).collect();
+        assert_eq!(names, ["seq1:1-4", "seq1:3-5", "seq1:7-10", "seq1:11-12"]);
+        assert_eq!(stats[1].soft_masked_bases, 1);
+        assert_eq!(stats[2].hard_masked_bases, 4);
+
+        let other_record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("seq2", None),
+            noodles_fasta::record::Sequence::from(b"ACGT".to_vec()),
+        );
+        assert!(process_fasta(None, &options)(&other_record, 1)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_alignment_mode() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("aligned_sequence", None),
+            noodles_fasta::record::Sequence::from(b"AC--GTa-cN".to_vec()),
+        );
+        assert!(process_fasta_record(&record, 1, None, &StatsOptions::default()).is_err());
+
+        let options = StatsOptions { alignment_mode: true, report_unexpected: true, ..Default::default() };
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        assert_eq!(stats.gap_dash_bases, Some(3));
+        assert_eq!(stats.alignment_length, Some(10));
+        assert_eq!(stats.sequence_length, 7);
+        assert_eq!(stats.non_masked_bases, 4);
+        assert_eq!(stats.soft_masked_bases, 2);
+        assert_eq!(stats.hard_masked_bases, 1);
+        assert_eq!(stats.gc_content, 3.0 / 7.0);
+        assert_eq!(stats.non_masked_ratio, 4.0 / 7.0);
+        assert!(stats.unexpected_bases.is_empty());
+        // The gaps split the regions, so each run of bases between them is a region of its own.
+        assert_eq!(stats.non_masked_region_count, 2);
+        assert_eq!(stats.soft_masked_region_count, 2);
+
+        let dash_mask_options = StatsOptions { mask_chars: Some("Nn-".to_string()), ..options.clone() };
+        let stats = process_fasta_record(&record, 1, None, &dash_mask_options)?.unwrap();
+        assert_eq!(stats.gap_dash_bases, Some(3));
+        assert_eq!(stats.n_count, 1);
+        assert_eq!(stats.hard_masked_bases, 1);
+
+        let stats = process_fasta_record(&record, 1, None, &StatsOptions { checksum_only: true, ..options })?.unwrap();
+        assert_eq!(stats.gap_dash_bases, Some(3));
+        assert_eq!(stats.sequence_length, 7);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_non_iupac_codes_ok() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("iupac_test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"mMrvy".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            ignore_iupac: true,
+            ..Default::default()
+        };
+        let stats = process_fasta_record(&record, 1, None, &options)?;
+        assert!(stats.is_some());
+        let stats = stats.unwrap();
+
+        // No BED files should have been created.
+        let non_masked_bed_path = tmpdir.path().join("test_sequence.non-masked.bed");
+        assert!(!non_masked_bed_path.exists());       
+        let soft_masked_bed_path = tmpdir.path().join("test_sequence.soft-masked.bed");
+        assert!(!soft_masked_bed_path.exists());
+        let hard_masked_bed_path = tmpdir.path().join("test_sequence.hard-masked.bed");
+        assert!(!hard_masked_bed_path.exists());
+
+        assert_eq!(stats.sequence_name, "iupac_test_sequence");
+        assert_eq!(stats.non_masked_bases, 0);
+        assert_eq!(stats.non_masked_ratio, 0.0);
+
+        assert_eq!(stats.soft_masked_bases, 0);
+        assert_eq!(stats.soft_masked_ratio, 0.0);
+
+        assert_eq!(stats.hard_masked_bases, 0);
+        assert_eq!(stats.hard_masked_ratio, 0.0);
+        
+        assert_eq!(stats.gc_content, 0.0);
+        assert_eq!(stats.iupac_ambiguous_bases, 5);
+        assert_eq!(stats.other_bases, 0);
+        assert_eq!(stats.a_count + stats.c_count + stats.g_count + stats.t_count + stats.n_count, 0);
+        assert_eq!(stats.sequence_length, 5);
+        assert_eq!(stats.checksum_sha256, "fed4eff301b269d726775e3a29cdc4b015b3b5d9e2d771ecf9964ccac590d30f");
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_ignore_iupac() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("ignore_iupac_test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACRGTYwX".to_vec()),
+        );
+
+        assert_eq!(
+            process_fasta_record(&record, 1, None, &StatsOptions::default()).unwrap_err(),
+            ProcessError::UnexpectedBase {
+                base: b'R',
+                sequence: "ignore_iupac_test_sequence".to_string()
+            }
+        );
+
+        let stats = process_fasta_record(&record, 1, None, &StatsOptions { ignore_iupac: true, ..Default::default() })?.unwrap();
+        assert_eq!(stats.non_masked_bases, 4);
+        assert_eq!(stats.iupac_ambiguous_bases, 3);
+        assert_eq!(stats.other_bases, 1);
+        assert_eq!(stats.sequence_length, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn gc_contribution_ok() {
+        assert_eq!(gc_contribution(b'G'), Some(1.0));
+        assert_eq!(gc_contribution(b's'), Some(1.0));
+        assert_eq!(gc_contribution(b'a'), Some(0.0));
+        assert_eq!(gc_contribution(b'W'), Some(0.0));
+        assert_eq!(gc_contribution(b'r'), Some(0.5));
+        assert_eq!(gc_contribution(b'B'), Some(2.0 / 3.0));
+        assert_eq!(gc_contribution(b'h'), Some(1.0 / 3.0));
+        assert_eq!(gc_contribution(b'N'), None);
+        assert_eq!(gc_contribution(b'X'), None);
+    }
+
+    #[test]
+    fn process_fasta_record_iupac_aware() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("iupac_aware_test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"GCATSWRyNN".to_vec()),
+        );
+
+        let stats = process_fasta_record(&record, 1, None, &StatsOptions { iupac_aware: true, ..Default::default() })?.unwrap();
+        assert_eq!(stats.gc_content, 4.0 / 8.0);
+        assert_eq!(stats.iupac_ambiguous_bases, 4);
+        assert_eq!(stats.hard_masked_bases, 2);
+
+        let stats = process_fasta_record(&record, 1, None, &StatsOptions { ignore_iupac: true, ..Default::default() })?.unwrap();
+        assert_eq!(stats.gc_content, 2.0 / 10.0);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_checksums() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("checksum_test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGT".to_vec()),
+        );
+        let sha256 = "1dff3e84fe7877e0673b69bbddcf40124e396e3f9943dd890c91b6a09adb9af0";
+        let md5 = "f1f8f4bf413b16ad135722aa4591043e";
+
+        let stats = process_fasta_record(&record, 1, None, &StatsOptions::default())?.unwrap();
+        assert_eq!(stats.checksum_sha256, sha256);
+        assert_eq!(stats.checksum_md5, None);
+
+        let stats = process_fasta_record(&record, 1, None, &StatsOptions { checksum_kind: ChecksumKind::Md5, ..Default::default() })?.unwrap();
+        assert_eq!(stats.checksum_sha256, "");
+        assert_eq!(stats.checksum_md5, Some(md5.to_string()));
+
+        let stats = process_fasta_record(&record, 1, None, &StatsOptions { checksum_kind: ChecksumKind::Both, ..Default::default() })?.unwrap();
+        assert_eq!(stats.checksum_sha256, sha256);
+        assert_eq!(stats.checksum_md5, Some(md5.to_string()));
+        assert_eq!(stats.checksum_blake3, None);
+
+        let stats = process_fasta_record(&record, 1, None, &StatsOptions { checksum_kind: ChecksumKind::Blake3, ..Default::default() })?.unwrap();
+        assert_eq!(stats.checksum_sha256, "");
+        assert_eq!(stats.checksum_md5, None);
+        assert_eq!(stats.checksum_blake3, Some(blake3::hash(b"ACGT").to_hex().to_string()));
+
+        let options = StatsOptions { checksum_kind: ChecksumKind::Both, no_checksum: true, ..Default::default() };
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        assert_eq!(stats.checksum_sha256, "");
+        assert_eq!(stats.checksum_md5, None);
+        assert_eq!(stats.non_masked_bases, 4);
+        assert_eq!(stats.gc_content, 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn checksum_implementations() {
+        let checksum = |mut checksum: Box<dyn Checksum>| {
+            checksum.update(b"AC");
+            checksum.update(b"GT");
+            checksum.finalize()
+        };
+        assert_eq!(
+            checksum(ChecksumAlgorithm::Sha256.checksum()),
+            "1dff3e84fe7877e0673b69bbddcf40124e396e3f9943dd890c91b6a09adb9af0"
+        );
+        assert_eq!(checksum(ChecksumAlgorithm::Md5.checksum()), "f1f8f4bf413b16ad135722aa4591043e");
+        assert_eq!(
+            ChecksumAlgorithm::Blake3.checksum().finalize(),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+    }
+
+    #[test]
+    fn canonical_checksum_ok() -> Result<(), Box<dyn Error>> {
+        let sha256 = |sequence: &[u8]| format!("{:x}", Sha256::digest(sequence));
+        // The reverse complement of ACGTT is AACGT, which sorts first.
+        assert_eq!(canonical_checksum(b"ACGTT"), sha256(b"AACGT"));
+        assert_eq!(canonical_checksum(b"aacgt"), sha256(b"AACGT"));
+        assert_eq!(canonical_checksum(b"AACGT"), sha256(b"AACGT"));
+        assert_eq!(canonical_checksum(b"NNCCA"), sha256(b"NNCCA"));
+        assert_eq!(canonical_checksum(b"TGGNN"), sha256(b"NNCCA"));
+        assert_eq!(canonical_checksum(b""), sha256(b""));
+
+        let sequence: Vec<u8> = b"GATTACA".iter().copied().cycle().take(3 * CHECKSUM_BLOCK_SIZE + 17).collect();
+        let reverse_complement: Vec<u8> = sequence.iter().rev().map(|base| complement(*base)).collect();
+        assert_eq!(canonical_checksum(&sequence), canonical_checksum(&reverse_complement));
+        assert_eq!(canonical_checksum(&sequence), sha256(&sequence.clone().min(reverse_complement)));
+
+        let options = StatsOptions { canonical_checksum: true, ..Default::default() };
+        let stats = compute_statistics("test_sequence", b"ACGTT", &options)?;
+        assert_eq!(stats.checksum_canonical, Some(sha256(b"AACGT")));
+        assert_eq!(compute_statistics("empty_sequence", b"", &options)?.checksum_canonical, Some("".to_string()));
+        assert_eq!(compute_statistics("test_sequence", b"ACGTT", &StatsOptions::default())?.checksum_canonical, None);
+        Ok(())
+    }
+
+    #[test]
+    fn read_summary_ok() -> Result<(), Box<dyn Error>> {
+        let stats = vec![
+            compute_statistics("chr1", b"ACGTNNacgt", &StatsOptions::default())?,
+            compute_statistics("chr2", b"", &StatsOptions { canonical_checksum: true, ..Default::default() })?,
+        ];
+        let ndjson: String = stats.iter().map(|stats| serde_json::to_string(stats).unwrap() + "\n").collect();
+        for summary in [
+            serde_json::to_string_pretty(&SummaryReport::new(&stats))?,
+            serde_json::to_string_pretty(&stats)?,
+            ndjson,
+        ] {
+            let read_stats = read_summary(summary.as_bytes())?;
+            assert_eq!(serde_json::to_value(&read_stats)?, serde_json::to_value(&stats)?);
+        }
+        assert!(read_summary(&b"[{\"sequence_name\": \"chr1\"}]"[..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_previous_statistics() -> Result<(), Box<dyn Error>> {
+        let record = |sequence: &[u8]| {
+            FastaRecord::new(
+                noodles_fasta::record::Definition::new("chr1", None),
+                noodles_fasta::record::Sequence::from(sequence.to_vec()),
+            )
+        };
+        let mut previous = compute_statistics("chr1", b"ACGTACGT", &StatsOptions::default())?;
+        previous.a_count = 42;
+        let options = StatsOptions {
+            previous_statistics: Some(Arc::new(HashMap::from([("chr1".to_string(), previous)]))),
+            ..Default::default()
+        };
+        let process = |sequence: &[u8], options: &StatsOptions| {
+            process_fasta_record(&record(sequence), 1, None, options).map(|stats| stats.unwrap().a_count)
+        };
+        assert_eq!(process(b"ACGTACGT", &options)?, 42);
+        assert_eq!(process(b"ACGTACGTA", &options)?, 3);
+        assert_eq!(process(b"AAGTACGT", &options)?, 3);
+
+        // Without checksums, sequences with the same name and length are considered unchanged.
+        let no_checksum_options = StatsOptions { no_checksum: true, ..options.clone() };
+        assert_eq!(process(b"AAGTACGT", &no_checksum_options)?, 42);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_checksum_uppercase() -> Result<(), Box<dyn Error>> {
+        let soft_masked_record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("soft_masked_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACgtNn".to_vec()),
+        );
+        let non_masked_record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("non_masked_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGTNN".to_vec()),
+        );
+        let options = StatsOptions {
+            checksum_kind: ChecksumKind::Both,
+            ..Default::default()
+        };
+        let uppercase_options = StatsOptions {
+            checksum_uppercase: true,
+            ..options.clone()
+        };
+
+        let soft_masked_stats = process_fasta_record(&soft_masked_record, 1, None, &options)?.unwrap();
+        let non_masked_stats = process_fasta_record(&non_masked_record, 1, None, &options)?.unwrap();
+        assert_ne!(soft_masked_stats.checksum_sha256, non_masked_stats.checksum_sha256);
+        assert_ne!(soft_masked_stats.checksum_md5, non_masked_stats.checksum_md5);
+
+        let soft_masked_stats = process_fasta_record(&soft_masked_record, 1, None, &uppercase_options)?.unwrap();
+        let non_masked_stats = process_fasta_record(&non_masked_record, 1, None, &uppercase_options)?.unwrap();
+        assert_eq!(soft_masked_stats.checksum_sha256, non_masked_stats.checksum_sha256);
+        assert_eq!(soft_masked_stats.checksum_md5, non_masked_stats.checksum_md5);
+        assert_eq!(soft_masked_stats.soft_masked_bases, 2);
+        assert_eq!(non_masked_stats.soft_masked_bases, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_shannon_entropy() -> Result<(), Box<dyn Error>> {
+        let uniform_record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("uniform_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGTacgtNNNN".to_vec()),
+        );
+        let homopolymer_record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("homopolymer_sequence", None),
+            noodles_fasta::record::Sequence::from(b"AAAAaaaaNN".to_vec()),
+        );
+        let options = StatsOptions::default();
+
+        let uniform_stats = process_fasta_record(&uniform_record, 1, None, &options)?.unwrap();
+        assert_eq!(uniform_stats.shannon_entropy, 2.0);
+        let homopolymer_stats = process_fasta_record(&homopolymer_record, 1, None, &options)?.unwrap();
+        assert_eq!(homopolymer_stats.shannon_entropy, 0.0);
+        assert!(homopolymer_stats.shannon_entropy.is_sign_positive());
+        assert_eq!(shannon_entropy(&[1, 1, 0, 0]), 1.0);
+        assert_eq!(shannon_entropy(&[0, 0, 0, 0]), 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_longest_homopolymer() -> Result<(), Box<dyn Error>> {
+        let options = StatsOptions::default();
+        for (sequence, expected_length, expected_base) in [
+            (b"ACGGgTTTNNNNNA".to_vec(), 3, 'G'),
+            (b"aaCCCCgggg".to_vec(), 4, 'C'),
+            (b"NNNN".to_vec(), 0, '.'),
+            (b"".to_vec(), 0, '.'),
+        ] {
+            let record = FastaRecord::new(
+                noodles_fasta::record::Definition::new("homopolymer_sequence", None),
+                noodles_fasta::record::Sequence::from(sequence),
+            );
+            let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+            assert_eq!(stats.longest_homopolymer, expected_length);
+            assert_eq!(stats.longest_homopolymer_base, expected_base);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_gaps() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("gap_sequence", None),
+            noodles_fasta::record::Sequence::from(b"NNACGTnNNNacRgtNacgN".to_vec()),
+        );
+        let options = StatsOptions {
+            ignore_iupac: true,
+            ..Default::default()
+        };
+
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        assert_eq!(stats.num_gaps, 4);
+        assert_eq!(stats.gap_lengths, vec![2, 4, 1, 1]);
+        assert_eq!(stats.longest_ungapped_run, 5);
+
+        for (sequence, expected_run) in [(&b"ACGT"[..], 4), (b"NNNN", 0), (b"ACNGTTacgt", 7), (b"ACGTTNNacgN", 5)] {
+            let stats = compute_statistics("gap_sequence", sequence, &options)?;
+            assert_eq!(stats.longest_ungapped_run, expected_run, "{}", sequence.escape_ascii());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn write_gc_bedgraph_ok() -> Result<(), Box<dyn Error>> {
+        let mut output = Vec::new();
+        write_gc_bedgraph(&mut output, "chr1", b"GCGCATATNNNNNNNNggaNa", 4, Alphabet::Dna)?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "chr1\t0\t4\t1\n\
+            chr1\t4\t8\t0\n\
+            chr1\t16\t20\t0.6666666666666666\n\
+            chr1\t20\t21\t0\n"
+        );
+
+        let mut output = Vec::new();
+        write_gc_bedgraph(&mut output, "rna1", b"GCUUAUGCu", 4, Alphabet::Rna)?;
+        assert_eq!(String::from_utf8(output)?, "rna1\t0\t4\t0.5\nrna1\t4\t8\t0.5\nrna1\t8\t9\t0\n");
+        Ok(())
+    }
+
+    #[test]
+    fn write_gc_profile_ok() -> Result<(), Box<dyn Error>> {
+        let mut output = Vec::new();
+        // Bins of 3 bases, with the last one also containing the remaining 2 bases.
+        write_gc_profile(&mut output, b"GCGATANNNccaTT", 4, Alphabet::Dna)?;
+        assert_eq!(String::from_utf8(output)?, "bin_index\tgc_fraction\n0\t1\n1\t0\n2\tNA\n3\t0.4\n");
+
+        let mut output = Vec::new();
+        write_gc_profile(&mut output, b"GA", 3, Alphabet::Dna)?;
+        assert_eq!(String::from_utf8(output)?, "bin_index\tgc_fraction\n0\tNA\n1\tNA\n2\t0.5\n");
+
+        // Custom mask characters (like `X`) are not considered either.
+        let mut output = Vec::new();
+        write_gc_profile(&mut output, b"GGXXGXXXXX", 2, Alphabet::Dna)?;
+        assert_eq!(String::from_utf8(output)?, "bin_index\tgc_fraction\n0\t1\n1\tNA\n");
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_gc_bedgraph() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGTacgtNNNNGGC".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            no_bed_output: true,
+            gc_window: Some(10),
+            ..Default::default()
+        };
+        process_fasta_record(&record, 1, None, &options)?;
+        assert!(!tmpdir.path().join("test_sequence.non-masked.bed").exists());
+        let bedgraph = std::fs::read_to_string(tmpdir.path().join("test_sequence.gc.bedgraph"))?;
+        assert_eq!(bedgraph, "test_sequence\t0\t10\t0.5\ntest_sequence\t10\t15\t1\n");
+
+        // Custom mask characters are not considered either, like for the GC content without them in the summary.
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("masked_sequence", None),
+            noodles_fasta::record::Sequence::from(b"GGXXGGXX".to_vec()),
+        );
+        let options = StatsOptions { mask_chars: Some("NnXx".to_string()), ..options };
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        assert_eq!(stats.gc_content_no_n, 1.0);
+        let bedgraph = std::fs::read_to_string(tmpdir.path().join("masked_sequence.gc.bedgraph"))?;
+        assert_eq!(bedgraph, "masked_sequence\t0\t8\t1\n");
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_mask_bedgraph() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGTacgtNNNNGGCAAAAAnna".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            no_bed_output: true,
+            mask_window: Some(5),
+            ..Default::default()
+        };
+        process_fasta_record(&record, 1, None, &options)?;
+        let bedgraph = std::fs::read_to_string(tmpdir.path().join("test_sequence.mask.bedgraph"))?;
+        assert_eq!(
+            bedgraph,
+            "test_sequence\t0\t5\t0.2\n\
+            test_sequence\t5\t10\t1\n\
+            test_sequence\t10\t15\t0.4\n\
+            test_sequence\t15\t20\t0\n\
+            test_sequence\t20\t23\t1\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_gc_skew_track() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"GGGcaACCCAgcccaNNTG".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            no_bed_output: true,
+            gc_skew_window: Some(5),
+            ..Default::default()
+        };
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        let bedgraph = std::fs::read_to_string(tmpdir.path().join("test_sequence.gcskew.bedgraph"))?;
+        assert_eq!(
+            bedgraph,
+            "test_sequence\t0\t5\t0.5\n\
+            test_sequence\t5\t10\t-0.5\n\
+            test_sequence\t10\t15\t-1\n\
+            test_sequence\t15\t19\t0\n"
+        );
+        assert_eq!(stats.gc_skew_min_position, Some(15));
+        assert_eq!(stats.gc_skew_max_position, Some(5));
+
+        let stats = compute_statistics("test_sequence", b"GGGcaACCCA", &StatsOptions::default())?;
+        assert_eq!(stats.gc_skew_min_position, None);
+        assert!(!serde_json::to_string(&stats)?.contains("gc_skew_min_position"));
+        Ok(())
+    }
+
+    #[test]
+    fn compute_statistics_softmask_weight() -> Result<(), Box<dyn Error>> {
+        let weighted_gc_content = |weight| -> Result<f64, ProcessError> {
+            let options = StatsOptions { softmask_weight: weight, ..Default::default() };
+            Ok(compute_statistics("test_sequence", b"ACGTacgtNN", &options)?.gc_content_weighted)
+        };
+        assert_eq!(weighted_gc_content(None)?, 0.4);
+        assert_eq!(weighted_gc_content(Some(1.0))?, 0.4);
+        assert_eq!(weighted_gc_content(Some(0.5))?, 3.0 / 8.0);
+        assert_eq!(weighted_gc_content(Some(0.0))?, 2.0 / 6.0);
+        // The weight is clamped to [0, 1].
+        assert_eq!(weighted_gc_content(Some(2.0))?, 0.4);
+        assert_eq!(weighted_gc_content(Some(-1.0))?, 2.0 / 6.0);
+
+        let options = StatsOptions { softmask_weight: Some(0.0), ..Default::default() };
+        assert_eq!(compute_statistics("soft_only", b"acgt", &options)?.gc_content_weighted, 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn compute_statistics_ok() -> Result<(), Box<dyn Error>> {
+        let stats = compute_statistics("test_sequence", b"ACGTacgtNN", &StatsOptions::default())?;
+        assert_eq!(stats.sequence_name, "test_sequence");
+        assert_eq!(stats.non_masked_bases, 4);
+        assert_eq!(stats.soft_masked_bases, 4);
+        assert_eq!(stats.hard_masked_bases, 2);
+        assert_eq!(stats.gc_content, 0.4);
+        assert_eq!(stats.gc_content_no_n, 0.5);
+        assert_eq!(stats.sequence_length, 10);
+
+        let empty_stats = compute_statistics("empty_sequence", b"", &StatsOptions::default())?;
+        assert_eq!(empty_stats.sequence_length, 0);
+        assert_eq!(empty_stats.gc_content, 0.0);
+        assert_eq!(empty_stats.gc_content_no_n, 0.0);
+
+        let all_n_stats = compute_statistics("all_n_sequence", b"NNnn", &StatsOptions::default())?;
+        assert_eq!(all_n_stats.gc_content, 0.0);
+        assert_eq!(all_n_stats.gc_content_no_n, 0.0);
+        assert_eq!(empty_stats.checksum_sha256, "");
+
+        assert_eq!(
+            compute_statistics("invalid_sequence", b"ACGTX", &StatsOptions::default()).unwrap_err(),
+            ProcessError::UnexpectedBase {
+                base: b'X',
+                sequence: "invalid_sequence".to_string()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_errors() {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("error_test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGTmXT".to_vec()),
+        );
+
+        assert_eq!(
+            process_fasta_record(&record, 1, None, &StatsOptions::default()).unwrap_err(),
+            ProcessError::UnexpectedBase {
+                base: b'm',
+                sequence: "error_test_sequence".to_string()
+            }
+        );
+        assert_eq!(
+            process_fasta_record(&record, 1, None, &StatsOptions { match_regex: Some("(".to_string()), ..Default::default() })
+                .unwrap_err(),
+            ProcessError::InvalidRegex("(".to_string())
+        );
+    }
+
+    #[test]
+    fn process_fasta_record_duplicates() -> Result<(), Box<dyn Error>> {
+        let first_record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("dup", None),
+            noodles_fasta::record::Sequence::from(b"ACGTacgt".to_vec()),
+        );
+        let second_record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("dup", None),
+            noodles_fasta::record::Sequence::from(b"ggNNCC".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            ..Default::default()
+        };
+        process_fasta_record(&first_record, 1, None, &options)?;
+        assert_eq!(
+            process_fasta_record(&second_record, 2, None, &options)
+                .unwrap_err(),
+            ProcessError::DuplicateName("dup".to_string())
+        );
+        assert_eq!(std::fs::read_to_string(tmpdir.path().join("dup.soft-masked.bed"))?, "dup\t4\t8\n");
+
+        let rename_options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            on_duplicate: DuplicatePolicy::Rename,
+            ..Default::default()
+        };
+        process_fasta_record(&second_record, 2, None, &rename_options)?;
+        assert_eq!(std::fs::read_to_string(tmpdir.path().join("dup.soft-masked.bed"))?, "dup\t4\t8\n");
+        assert_eq!(std::fs::read_to_string(tmpdir.path().join("dup.2.soft-masked.bed"))?, "dup\t0\t2\n");
+
+        let merge_options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            on_duplicate: DuplicatePolicy::Merge,
+            ..Default::default()
+        };
+        process_fasta_record(&second_record, 2, None, &merge_options)?;
+        assert_eq!(
+            std::fs::read_to_string(tmpdir.path().join("dup.soft-masked.bed"))?,
+            "dup\t4\t8\ndup\t0\t2\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(tmpdir.path().join("dup.non-masked.bed"))?,
+            "dup\t0\t4\ndup\t4\t6\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn merge_sequence_statistics_ok() -> Result<(), Box<dyn Error>> {
+        let first = compute_statistics("dup", b"ACGTTTTacgt", &StatsOptions::default())?;
+        let second = compute_statistics("dup", b"ggNNNCCn", &StatsOptions::default())?;
+        let merged = merge_sequence_statistics(&first, &second);
+
+        assert_eq!(merged.sequence_name, "dup");
+        assert_eq!(merged.sequence_length, 19);
+        assert_eq!(merged.non_masked_bases, 9);
+        assert_eq!(merged.soft_masked_bases, 6);
+        assert_eq!(merged.hard_masked_bases, 4);
+        assert_eq!(merged.soft_masked_ratio, 6.0 / 19.0);
+        assert_eq!(merged.gc_content, 8.0 / 19.0);
+        assert_eq!(merged.gc_content_no_n, 8.0 / 15.0);
+        assert_eq!(merged.gc_content_masked, 4.0 / 10.0);
+        assert_eq!(merged.gc_content_unmasked, 4.0 / 9.0);
+        assert_eq!(merged.at_content, 7.0 / 19.0);
+        assert_eq!(merged.gc_skew, 0.0);
+        assert_eq!(merged.at_skew, -3.0 / 7.0);
+        assert_eq!(merged.a_count, 2);
+        assert_eq!(merged.c_count, 4);
+        assert_eq!(merged.g_count, 4);
+        assert_eq!(merged.t_count, 5);
+        assert_eq!(merged.n_count, 4);
+        assert_eq!(merged.shannon_entropy, shannon_entropy(&[2, 4, 4, 5]));
+        assert_eq!(merged.longest_homopolymer, 4);
+        assert_eq!(merged.longest_homopolymer_base, 'T');
+        assert_eq!(merged.num_gaps, 2);
+        assert_eq!(merged.gap_lengths, vec![3, 1]);
+        assert_eq!(merged.checksum_sha256, "");
+        Ok(())
+    }
+
+    #[test]
+    fn round_statistics_ok() -> Result<(), Box<dyn Error>> {
+        let options = StatsOptions { dinucleotides: true, ..Default::default() };
+        let sequence = [&b"CCCCCCCCGGGGGGGG"[..], &b"A".repeat(54), b"aaaaa"].concat();
+        let mut stats = compute_statistics("test_sequence", &sequence, &options)?;
+        assert_eq!(stats.gc_content, 16.0 / 75.0);
+        assert_eq!(serde_json::to_value(&stats)?["gc_content"], 0.21333333333333335);
+
+        round_statistics(&mut stats, 3);
+        assert_eq!(stats.gc_content, 0.213);
+        assert_eq!(serde_json::to_value(&stats)?["gc_content"], 0.213);
+        assert_eq!(stats.soft_masked_ratio, 0.067);
+        assert_eq!(stats.shannon_entropy, 0.961);
+        assert_eq!(stats.cpg_observed_expected_ratio, Some(1.172));
+        assert_eq!(stats.sequence_length, 75);
+
+        round_statistics(&mut stats, 0);
+        assert_eq!(stats.gc_content, 0.0);
+        assert_eq!(stats.non_masked_ratio, 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn merge_statistics_ok() -> Result<(), Box<dyn Error>> {
+        let options = StatsOptions::default();
+        let first_part = vec![
+            compute_statistics("chr1", b"ACGTTTTacgt", &options)?,
+            compute_statistics("chr2", b"GGGGG", &options)?,
+        ];
+        let second_part = vec![compute_statistics("chr1", b"ggNNNCCn", &options)?];
+        let merged = merge_statistics(vec![first_part, second_part]);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].sequence_name, "chr1");
+        assert_eq!(merged[0].sequence_length, 19);
+        assert_eq!(merged[0].soft_masked_bases, 6);
+        assert_eq!(merged[0].hard_masked_bases, 4);
+        assert_eq!(merged[0].gc_content, 8.0 / 19.0);
+        assert_eq!(merged[0].gap_lengths, vec![3, 1]);
+        assert_eq!(merged[0].checksum_sha256, "");
+        // Sequences that only appear in one part are kept as they are.
+        assert_eq!(merged[1].sequence_name, "chr2");
+        assert_eq!(merged[1].checksum_sha256, compute_statistics("chr2", b"GGGGG", &options)?.checksum_sha256);
+        assert!(merge_statistics(Vec::new()).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn find_duplicate_sequences_ok() -> Result<(), Box<dyn Error>> {
+        let options = StatsOptions::default();
+        let stats = vec![
+            compute_statistics("contig1", b"ACGTNN", &options)?,
+            compute_statistics("contig2", b"GGCC", &options)?,
+            compute_statistics("contig3", b"ACGTNN", &options)?,
+            compute_statistics("empty1", b"", &options)?,
+            compute_statistics("contig4", b"acgtNN", &options)?,
+            compute_statistics("empty2", b"", &options)?,
+        ];
+        let duplicates = find_duplicate_sequences(&stats);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[&stats[0].checksum_sha256], vec!["contig1", "contig3"]);
+
+        // With uppercase checksums, soft-masking is ignored.
+        let options = StatsOptions { checksum_uppercase: true, ..options };
+        let stats = vec![
+            compute_statistics("contig1", b"ACGTNN", &options)?,
+            compute_statistics("contig4", b"acgtNN", &options)?,
+        ];
+        assert_eq!(find_duplicate_sequences(&stats)[&stats[0].checksum_sha256], vec!["contig1", "contig4"]);
+        assert!(find_duplicate_sequences(&[]).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn total_sequence_statistics_ok() -> Result<(), Box<dyn Error>> {
+        let stats = vec![
+            compute_statistics("chr1", b"ACGTTTTacgt", &StatsOptions::default())?,
+            compute_statistics("chr2", b"ggNNNCCn", &StatsOptions::default())?,
+            compute_statistics("chr3", b"GGGGG", &StatsOptions::default())?,
+        ];
+        let total = total_sequence_statistics(&stats).unwrap();
+
+        assert_eq!(total.sequence_name, "TOTAL");
+        assert_eq!(total.sequence_length, 24);
+        assert_eq!(total.non_masked_bases, 14);
+        assert_eq!(total.soft_masked_bases, 6);
+        assert_eq!(total.hard_masked_bases, 4);
+        assert_eq!(total.non_masked_ratio, 14.0 / 24.0);
+        assert_eq!(total.gc_content, 13.0 / 24.0);
+        assert_eq!(total.g_count, 9);
+        assert_eq!(total.longest_homopolymer, 5);
+        assert_eq!(total.longest_homopolymer_base, 'G');
+        assert_eq!(total.num_gaps, 2);
+        assert_eq!(total.gap_lengths, vec![3, 1]);
+        assert_eq!(total.checksum_sha256, "");
+
+        let single_total = total_sequence_statistics(&stats[..1]).unwrap();
+        assert_eq!(single_total.sequence_name, "TOTAL");
+        assert_eq!(single_total.checksum_sha256, "");
+        assert!(total_sequence_statistics(&[]).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn total_sequence_statistics_many_records() -> Result<(), Box<dyn Error>> {
+        // Lists are collected at once instead of per merge, which would take quadratic time for this many records.
+        let options = StatsOptions { soft_mask_region_lengths: true, ..Default::default() };
+        let mut record_stats = compute_statistics("contig", b"ACGTacNNgt", &options)?;
+        record_stats.output_files = ["non-masked", "soft-masked", "hard-masked"]
+            .map(|bed_ending| (PathBuf::from(format!("contig.{}.bed", bed_ending)), "bed"))
+            .to_vec();
+        let stats = vec![record_stats; 50_000];
+
+        let total = total_sequence_statistics(&stats).unwrap();
+        assert_eq!(total.sequence_length, 500_000);
+        assert_eq!(total.gap_lengths, vec![2; 50_000]);
+        assert_eq!(total.soft_mask_region_lengths, Some([2, 2].repeat(50_000)));
+        // The total is not written into any file.
+        assert!(total.output_files.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn sanitize_filename_ok() -> Result<(), Box<dyn Error>> {
+        assert_eq!(sanitize_filename("chr1_random.1-2"), "chr1_random.1-2");
+
+        let sanitized = sanitize_filename("gi|123|ref|NC_000001.1/x");
+        assert!(sanitized.starts_with("gi_123_ref_NC_000001.1_x_"));
+        assert_eq!(sanitized.len(), "gi_123_ref_NC_000001.1_x_".len() + 8);
+        assert_ne!(sanitized, sanitize_filename("gi/123/ref/NC_000001.1|x"));
+        assert_ne!(sanitize_filename("a b"), sanitize_filename("a_b"));
+
+        // The original name is kept inside the BED records.
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("gi|123|ref|NC_000001.1/x", None),
+            noodles_fasta::record::Sequence::from(b"ACGT".to_vec()),
+        );
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        assert_eq!(stats.sequence_name, "gi|123|ref|NC_000001.1/x");
+        let non_masked_bed = std::fs::read_to_string(tmpdir.path().join(format!("{}.non-masked.bed", sanitized)))?;
+        assert_eq!(non_masked_bed, "gi|123|ref|NC_000001.1/x\t0\t4\n");
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_record_finite_ratios() -> Result<(), Box<dyn Error>> {
+        let options = StatsOptions {
+            ignore_iupac: true,
+            ..Default::default()
+        };
+        for sequence in [&b"NNNNN"[..], b"n", b"A", b"c", b"R", b"-", b""] {
+            let record = FastaRecord::new(
+                noodles_fasta::record::Definition::new("test_sequence", None),
+                noodles_fasta::record::Sequence::from(sequence.to_vec()),
+            );
+            let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+            for ratio in [
+                stats.non_masked_ratio,
+                stats.soft_masked_ratio,
+                stats.hard_masked_ratio,
+                stats.gc_content,
+                stats.gc_content_no_n,
+                stats.at_content,
+                stats.gc_skew,
+                stats.at_skew,
+                stats.shannon_entropy,
+            ] {
+                assert!(ratio.is_finite(), "Non-finite ratio for sequence {:?}: {:?}", sequence.as_bstr(), stats);
+            }
+        }
+
+        let all_n_stats = compute_statistics("all_n_sequence", b"NNNNN", &StatsOptions::default())?;
+        assert_eq!(all_n_stats.hard_masked_ratio, 1.0);
+        assert_eq!(all_n_stats.gc_content, 0.0);
+        assert_eq!(all_n_stats.gc_content_no_n, 0.0);
+        assert_eq!(all_n_stats.shannon_entropy, 0.0);
+
+        let single_base_stats = compute_statistics("single_base_sequence", b"g", &StatsOptions::default())?;
+        assert_eq!(single_base_stats.soft_masked_ratio, 1.0);
+        assert_eq!(single_base_stats.gc_content, 1.0);
+        assert_eq!(single_base_stats.gc_content_no_n, 1.0);
+        assert_eq!(single_base_stats.shannon_entropy, 0.0);
+        assert_eq!(safe_ratio(3, 0), 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn natural_cmp_ok() {
+        let mut names = vec![
+            "scaffold_01", "chr10", "chrX", "chr2a", "chr1_random", "chr2", "scaffold_1", "chr1", "chrUn_KI270302v1",
+            "chr100", "chr",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            [
+                "chr", "chr1", "chr1_random", "chr2", "chr2a", "chr10", "chr100", "chrUn_KI270302v1", "chrX",
+                "scaffold_1", "scaffold_01",
+            ]
+        );
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+        assert_eq!(natural_cmp("seq007", "seq7"), Ordering::Greater);
+        assert_eq!(natural_cmp("seq", "seq"), Ordering::Equal);
+        assert_eq!(natural_cmp("99999999999999999999999", "100000000000000000000000"), Ordering::Less);
+    }
+
+    #[test]
+    fn process_fasta_record_dinucleotides() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"CGcgNCGAAA".to_vec()),
+        );
+        let stats = process_fasta_record(&record, 1, None, &StatsOptions::default())?.unwrap();
+        assert_eq!(stats.dinucleotide_counts, None);
+        assert_eq!(stats.cpg_count, None);
+        assert!(!serde_json::to_string(&stats)?.contains("cpg"));
+
+        let options = StatsOptions {
+            dinucleotides: true,
+            ..Default::default()
+        };
+        let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+        let counts = stats.dinucleotide_counts.clone().unwrap();
+        assert_eq!(counts.len(), 16);
+        assert_eq!(counts["CG"], 3);
+        assert_eq!(counts["GC"], 1);
+        assert_eq!(counts["GA"], 1);
+        assert_eq!(counts["AA"], 2);
+        assert_eq!(counts.values().sum::<usize>(), 7);
+        assert_eq!(stats.cpg_count, Some(3));
+        // 3 CpGs * 9 bases / (3 C * 3 G)
+        assert_eq!(stats.cpg_observed_expected_ratio, Some(3.0));
+        let json = serde_json::to_string(&stats)?;
+        assert!(json.contains("\"dinucleotide_counts\":{\"AA\":2,\"AC\":0,\"AG\":0,\"AT\":0,\"CA\":0,\"CC\":0,\"CG\":3,"));
+
+        let merged = merge_sequence_statistics(&stats, &stats);
+        assert_eq!(merged.dinucleotide_counts.unwrap()["CG"], 6);
+        assert_eq!(merged.cpg_count, Some(6));
+        assert_eq!(merged.cpg_observed_expected_ratio, Some(3.0));
+
+        let no_gc_stats = compute_statistics("no_gc_sequence", b"ATAT", &options)?;
+        assert_eq!(no_gc_stats.cpg_observed_expected_ratio, Some(0.0));
+        Ok(())
+    }
+
+    #[test]
+    fn melting_temperature_ok() -> Result<(), Box<dyn Error>> {
+        let options = StatsOptions { tm: true, ..Default::default() };
+        // Wallace rule: 2 * 6 + 4 * 7
+        let stats = compute_statistics("primer", b"ACGTACGTacgtG", &options)?;
+        assert_eq!(stats.tm_celsius, Some(40.0));
+
+        // Salt-adjusted formula for 20 bp with 10 G/C
+        let stats = compute_statistics("primer", b"ACGTACGTACGTACGTACGT", &options)?;
+        let expected = 81.5 + 16.6 * 0.05f64.log10() + 41.0 * 10.0 / 20.0 - 675.0 / 20.0;
+        assert!((stats.tm_celsius.unwrap() - expected).abs() < 1e-9);
+        assert!((stats.tm_celsius.unwrap() - 46.6529).abs() < 1e-4);
+
+        let merged = merge_sequence_statistics(&stats, &stats);
+        let expected = 81.5 + 16.6 * 0.05f64.log10() + 41.0 * 20.0 / 40.0 - 675.0 / 40.0;
+        assert!((merged.tm_celsius.unwrap() - expected).abs() < 1e-9);
+
+        assert_eq!(compute_statistics("ambiguous", b"ACGTN", &options)?.tm_celsius, None);
+        assert_eq!(compute_statistics("empty", b"", &options)?.tm_celsius, None);
+        assert_eq!(compute_statistics("primer", b"ACGT", &StatsOptions::default())?.tm_celsius, None);
+        Ok(())
+    }
+
+    #[test]
+    fn count_canonical_kmers_ok() {
+        assert_eq!(
+            count_canonical_kmers(b"ACGTNacg", 2),
+            vec![("AC".to_string(), 3), ("CG".to_string(), 2)]
+        );
+        assert_eq!(count_canonical_kmers(b"AAAtttNN", 1), vec![("A".to_string(), 6)]);
+        assert!(count_canonical_kmers(b"ACGTN", 5).is_empty());
+
+        // Compare the rolling (dense and sparse) counting against a naive implementation.
+        let sequence = b"ACGGTCATTGACNNCGATCGGATTACAGGCATTACGACTAGCATCAGCGACTTTACGGACTAGCAGCATCGANNNACGT";
+        let reverse_complement = |kmer: &[u8]| -> Vec<u8> {
+            kmer.iter()
+                .rev()
+                .map(|base| match base {
+                    b'A' => b'T',
+                    b'C' => b'G',
+                    b'G' => b'C',
+                    _ => b'A',
+                })
+                .collect()
+        };
+        for k in [3, 10, 11, 12] {
+            let mut expected: BTreeMap<String, usize> = BTreeMap::new();
+            for kmer in sequence.windows(k).filter(|kmer| !kmer.contains(&b'N')) {
+                let canonical = kmer.to_vec().min(reverse_complement(kmer));
+                *expected.entry(String::from_utf8(canonical).unwrap()).or_insert(0) += 1;
+            }
+            assert_eq!(count_canonical_kmers(sequence, k), expected.into_iter().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn process_fasta_record_kmers() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("test_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGTNacg".to_vec()),
+        );
+
+        let tmpdir = tempfile::tempdir()?;
+        let options = StatsOptions {
+            output_dir: Some(tmpdir.path().to_path_buf()),
+            kmer_size: Some(2),
+            ..Default::default()
+        };
+        process_fasta_record(&record, 1, None, &options)?;
+        let kmers = std::fs::read_to_string(tmpdir.path().join("test_sequence.kmers.tsv"))?;
+        assert_eq!(kmers, "AC\t3\nCG\t2\n");
+        Ok(())
+    }
+
+    #[test]
+    fn statistics_iter_ok() {
+        let reader = fasta::io::Reader::new(&b">seq1\nACGT\nacgt\n>seq2\nACGTX\n>seq3\nNN\n"[..]);
+        let mut stats = statistics_iter(reader, StatsOptions::default());
+        let first_stats = stats.next().unwrap().unwrap();
+        assert_eq!(first_stats.sequence_name, "seq1");
+        assert_eq!(first_stats.soft_masked_bases, 4);
+        assert_eq!(
+            stats.next().unwrap().unwrap_err(),
+            ProcessError::UnexpectedBase {
+                base: b'X',
+                sequence: "seq2".to_string()
+            }
+        );
+        assert_eq!(stats.next().unwrap().unwrap().hard_masked_bases, 2);
+        assert!(stats.next().is_none());
+
+        let invalid_reader = fasta::io::Reader::new(&b"ACGT\n>seq1\nACGT\n"[..]);
+        let results: Vec<_> = statistics_iter(invalid_reader, StatsOptions::default()).collect();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(results[0], Err(ProcessError::ReadError(_))));
+    }
+
+    #[test]
+    fn process_fasta_record_rna() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("rna_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGUUacguNN".to_vec()),
+        );
+        assert_eq!(
+            process_fasta_record(&record, 1, None, &StatsOptions::default()).unwrap_err(),
+            ProcessError::UnexpectedBase {
+                base: b'U',
+                sequence: "rna_sequence".to_string()
+            }
+        );
+
+        for alphabet in [Alphabet::Rna, Alphabet::Auto] {
+            let tmpdir = tempfile::tempdir()?;
+            let options = StatsOptions {
+                output_dir: Some(tmpdir.path().to_path_buf()),
+                alphabet,
+                ..Default::default()
+            };
+            let stats = process_fasta_record(&record, 1, None, &options)?.unwrap();
+            assert_eq!(stats.non_masked_bases, 5);
+            assert_eq!(stats.soft_masked_bases, 4);
+            assert_eq!(stats.hard_masked_bases, 2);
+            assert_eq!(stats.t_count, 3);
+            assert_eq!(stats.gc_content, 4.0 / 11.0);
+            assert_eq!(stats.longest_homopolymer, 2);
+            assert_eq!(stats.sequence_length, 11);
+            let soft_masked_bed = std::fs::read_to_string(tmpdir.path().join("rna_sequence.soft-masked.bed"))?;
+            assert_eq!(soft_masked_bed, "rna_sequence\t5\t9\n");
+        }
+
+        let dna_record = FastaRecord::new(
+            noodles_fasta::record::Definition::new("dna_sequence", None),
+            noodles_fasta::record::Sequence::from(b"ACGT".to_vec()),
+        );
+        let rna_options = StatsOptions {
+            alphabet: Alphabet::Rna,
+            ..Default::default()
+        };
+        assert_eq!(
+            process_fasta_record(&dna_record, 1, None, &rna_options).unwrap_err(),
+            ProcessError::UnexpectedBase {
+                base: b'T',
+                sequence: "dna_sequence".to_string()
+            }
+        );
+        let auto_options = StatsOptions {
+            alphabet: Alphabet::Auto,
+            ..Default::default()
+        };
+        assert_eq!(process_fasta_record(&dna_record, 1, None, &auto_options)?.unwrap().t_count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn skew_ok() {
+        assert_eq!(skew(3, 1), 0.5);
+        assert_eq!(skew(1, 3), -0.5);
+        assert_eq!(skew(2, 0), 1.0);
+        assert_eq!(skew(0, 0), 0.0);
+
+        let stats = compute_statistics("test_sequence", b"GGGCaatN", &StatsOptions::default()).unwrap();
+        assert_eq!(stats.gc_skew, 0.5);
+        assert_eq!(stats.at_skew, 1.0 / 3.0);
+        assert_eq!(stats.at_content, 3.0 / 8.0);
+    }
 }
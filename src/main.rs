@@ -1,20 +1,100 @@
 use clap::Parser;
+use clap::ValueEnum;
 use fastats::*;
+use flate2::Compression;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use indicatif::ProgressBar;
+use indicatif::ProgressStyle;
+use noodles_bgzf as bgzf;
 use noodles_fasta as fasta;
 use noodles_fasta::Record as FastaRecord;
 use rayon::prelude::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::error::Error;
 use std::fs;
 use std::fs::File;
+use std::io::BufRead;
 use std::io::BufReader;
+use std::io::BufWriter;
 use std::io::ErrorKind;
+use std::io::IsTerminal;
+use std::num::NonZeroUsize;
+use std::io::Write;
+use std::path::Path;
 use std::path::PathBuf;
 use std::result::Result;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum OutputFormat {
+    Json,
+    Ndjson,
+    Tsv,
+    Csv,
+}
+
+impl OutputFormat {
+    fn file_extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Csv => "csv",
+        }
+    }
+}
+
+/// What to do when a record cannot be processed: stop reading further records (and write no summary), or process
+/// all other records and report the errors along with their statistics.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum ErrorPolicy {
+    Abort,
+    Continue,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    fn level_filter(&self) -> log::LevelFilter {
+        match self {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum SortOrder {
+    Lexical,
+    Natural,
+    Length,
+    Input,
+}
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    fasta_file: PathBuf,
+    #[arg(
+        required = true,
+        help = "The FASTA file(s) to analyze (or patterns like 'assemblies/*.fa'), or '-' to read from stdin. With multiple files, the per-sequence output files are stored in a subdirectory per FASTA file."
+    )]
+    fasta_files: Vec<PathBuf>,
 
     #[arg(
         short = 'o',
@@ -24,6 +104,18 @@ struct Cli {
     )]
     output_dir: PathBuf,
 
+    #[arg(
+        long = "bed-dir",
+        help = "The output directory for the per-sequence BED files, e.g., on scratch storage (default: the output directory)."
+    )]
+    bed_dir: Option<PathBuf>,
+
+    #[arg(
+        long = "prefix",
+        help = "Prefix for the names of all output files, separated by a dot (e.g., 'mygenome' writes 'mygenome.summary.json')."
+    )]
+    prefix: Option<String>,
+
     #[arg(
         short = 'q',
         long = "quiet",
@@ -32,6 +124,35 @@ struct Cli {
     )]
     quiet: bool,
 
+    #[arg(
+        long = "dry-run",
+        default_value = "false",
+        help = "Only read and validate all sequences, without writing any files (prints the number of sequences and any errors)."
+    )]
+    dry_run: bool,
+
+    #[arg(
+        long = "log-level",
+        value_enum,
+        default_value = "error",
+        help = "The level of the log messages on stderr: 'warn' reports skipped records, 'info' each processed record, 'debug' each written BED region."
+    )]
+    log_level: LogLevel,
+
+    #[arg(
+        long = "timings",
+        default_value = "false",
+        help = "Print the wall-clock time of the processing phases (reading, processing records, writing BED files, and writing the summary) on stderr."
+    )]
+    timings: bool,
+
+    #[arg(
+        long = "record-timings",
+        default_value = "false",
+        help = "Report the wall-clock time spent processing each record (as 'processing_ms'), e.g., to find sequences that dominate the runtime."
+    )]
+    record_timings: bool,
+
     #[arg(
         long = "ignore-iupac",
         default_value = "false",
@@ -39,6 +160,20 @@ struct Cli {
     )]
     ignore_iupac: bool,
 
+    #[arg(
+        long = "iupac-aware",
+        default_value = "false",
+        help = "Count ambiguous IUPAC codes fractionally towards the GC content (e.g., 'S' as 1.0, 'R' as 0.5), excluding 'N' from its denominator."
+    )]
+    iupac_aware: bool,
+
+    #[arg(
+        long = "report-unexpected",
+        default_value = "false",
+        help = "Store the positions of all bases other than ACGTN (e.g., tolerated via --ignore-iupac) into an 'unexpected_bases.tsv' file (at most 10000)."
+    )]
+    report_unexpected: bool,
+
     #[arg(
         long = "no-bed-output",
         default_value = "false",
@@ -46,6 +181,50 @@ struct Cli {
     )]
     no_bed_output: bool,
 
+    #[arg(
+        long = "combined-bed",
+        default_value = "false",
+        help = "Store the masking regions of all sequences into a single 'masking.bed' file, with the masking category in the fourth column."
+    )]
+    combined_bed: bool,
+
+    #[arg(
+        long = "only-summary",
+        default_value = "false",
+        conflicts_with_all = ["combined_bed", "compress_summary"],
+        help = "Only write the assembly statistics ('assembly.json'), without the summary file and any per-sequence files (implies --no-bed-output)."
+    )]
+    only_summary: bool,
+
+    #[arg(
+        long = "bed-columns",
+        value_enum,
+        default_value = "3",
+        help = "The number of columns of the BED files: 4 adds the masking category as name, 5 additionally adds the region length (capped at 1000) as score."
+    )]
+    bed_columns: BedColumns,
+
+    #[arg(
+        long = "compress-bed",
+        default_value = "false",
+        help = "Compress the BED files with gzip (and store them as '.bed.gz')."
+    )]
+    compress_bed: bool,
+
+    #[arg(
+        long = "compress-summary",
+        default_value = "false",
+        help = "Compress the summary file with gzip (and store it as, e.g., 'summary.json.gz'). The summary printed on stdout is not compressed."
+    )]
+    compress_summary: bool,
+
+    #[arg(
+        long = "merge-mask-bed",
+        default_value = "false",
+        help = "Additionally store the soft- and hard-masked regions merged into a single 'masked' category, in a '<sequence>.masked.bed' file."
+    )]
+    merge_mask_bed: bool,
+
     #[arg(
         long = "match-regex",
         default_value = ".*",
@@ -53,74 +232,2195 @@ struct Cli {
     )]
     sequence_match_regex: String,
 
-}
+    #[arg(
+        long = "exclude-regex",
+        help = "Regular expression to exclude sequences from the analysis, even if they match the regular expression of '--match-regex'."
+    )]
+    sequence_exclude_regex: Option<String>,
 
-impl Cli {
-    fn validate(self: &Cli) -> Result<(), std::io::Error> {
-        if !self.fasta_file.is_file() {
-            Err(std::io::Error::new(
-                ErrorKind::InvalidInput,
-                format!("The input file '{:?}' is not a file.", self.fasta_file),
-            ))
-        } else if self.output_dir.is_file() {
-            Err(std::io::Error::new(
-                ErrorKind::InvalidInput,
-                format!("The output directory '{:?}' is a file.", self.output_dir),
-            ))
-        } else if !self.output_dir.exists() {
-            fs::create_dir_all(&self.output_dir)
-        } else {
-            Ok(())
-        }
-    }
+    #[arg(
+        long = "case-insensitive-names",
+        default_value = "false",
+        help = "Match sequence names case-insensitively with '--match-regex' and '--exclude-regex' (e.g., if some files use 'Chr1' and others 'chr1')."
+    )]
+    case_insensitive_names: bool,
 
-    fn bed_output_dir(&self) -> Option<&PathBuf> {
-        if self.no_bed_output {
-            None
-        } else {
-            Some(&self.output_dir)
-        }
-    }
-}
+    #[arg(
+        long = "group-by-regex",
+        help = "Regular expression with one capture group (e.g., '(sample\\d+)_.*'): sequences are grouped by the captured part of their name, and assembly statistics per group are written into 'groups.json'."
+    )]
+    group_by_regex: Option<String>,
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args = Cli::parse();
-    args.validate().expect("Failed to validate CLI arguments");
+    #[arg(
+        long = "nx-curve",
+        default_value = "false",
+        help = "Write the Nx length for x from 0 to 100 percent into 'nx_curve.tsv', e.g., for plotting the contiguity of an assembly."
+    )]
+    nx_curve: bool,
 
-    let mut reader = File::open(&args.fasta_file)
-        .map(BufReader::new)
-        .map(fasta::io::Reader::new)?;
-    let records: Vec<FastaRecord> = reader.records().collect::<Result<_, _>>()?;
+    #[arg(
+        long = "pfm",
+        default_value = "false",
+        conflicts_with = "resume_from",
+        help = "Write the counts of A, C, G, and T at each position over all sequences into 'pfm.tsv' (a position frequency matrix, e.g., of oligos or motifs). All sequences must have the same length."
+    )]
+    pfm: bool,
 
-    let mut sequence_statistics: Vec<SequenceStatistics> = records
-        .par_iter()
-        .flat_map(process_fasta(args.bed_output_dir().map(|pb| pb.as_path()), args.sequence_match_regex.as_str(), args.ignore_iupac))
-        .collect();
-    sequence_statistics.sort_by_key(|s| s.sequence_name.clone());
+    #[arg(
+        long = "regions",
+        help = "BED file with regions (e.g., exons): only the bases within these regions are analyzed, sequences without any region are skipped, and no per-sequence files are written."
+    )]
+    regions: Option<PathBuf>,
 
-    let json_output = serde_json::to_string_pretty(&sequence_statistics).unwrap();
-    if !args.quiet {
-        println!("{}", json_output.clone());
-    }
-    fs::write(args.output_dir.join("summary.json"), json_output)?;
-    Ok(())
-}
+    #[arg(
+        long = "per-region",
+        default_value = "false",
+        requires = "regions",
+        help = "Report the statistics of each region of '--regions' separately (named '<sequence>:<start>-<end>', 1-based), instead of those of all regions per sequence."
+    )]
+    per_region: bool,
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[arg(
+        long = "resume-from",
+        conflicts_with = "regions",
+        help = "A summary of a previous run (JSON or NDJSON): the statistics of sequences with the same name, length, and checksum(s) are copied from it instead of being computed again (their per-sequence files are not written again)."
+    )]
+    resume_from: Option<PathBuf>,
 
-    #[test]
-    fn cli_validation() {
-        let cli = Cli {
-            fasta_file: PathBuf::from("does-not-exist.fasta"),
-            output_dir: PathBuf::from("output"),
-            quiet: false,
-            ignore_iupac: false,
-            no_bed_output: false,
-            sequence_match_regex: ".*".to_string(),
-        };
-        // Test invalid input file
-        assert!(cli.validate().is_err());
+    #[arg(
+        long = "min-length",
+        help = "Only analyze sequences with at least this length."
+    )]
+    min_length: Option<usize>,
+
+    #[arg(
+        long = "max-length",
+        help = "Only analyze sequences with at most this length."
+    )]
+    max_length: Option<usize>,
+
+    #[arg(
+        long = "max-records",
+        help = "Only read the first N records (over all input files), e.g., for a quick check of a huge FASTA file."
+    )]
+    max_records: Option<NonZeroUsize>,
+
+    #[arg(
+        long = "warn-short",
+        default_value = "0",
+        help = "Print a warning on stderr for each sequence shorter than this length (e.g., from a truncated download). Empty sequences are always reported, unless --quiet is set."
+    )]
+    warn_short: usize,
+
+    #[arg(
+        long = "expect-gc-min",
+        help = "Fail (with a nonzero exit code) if the total GC content of all sequences is below this fraction (between 0 and 1), e.g., as a QC check."
+    )]
+    expect_gc_min: Option<f64>,
+
+    #[arg(
+        long = "expect-gc-max",
+        help = "Fail (with a nonzero exit code) if the total GC content of all sequences is above this fraction (between 0 and 1)."
+    )]
+    expect_gc_max: Option<f64>,
+
+    #[arg(
+        long = "expect-gc-per-sequence",
+        default_value = "false",
+        help = "Check the GC content of each (non-empty) sequence against '--expect-gc-min' and '--expect-gc-max', instead of the total."
+    )]
+    expect_gc_per_sequence: bool,
+
+    #[arg(
+        long = "format",
+        value_enum,
+        default_value = "json",
+        help = "The format of the summary statistics (on stdout and in the summary file)."
+    )]
+    format: OutputFormat,
+
+    #[arg(
+        long = "legacy-json",
+        default_value = "false",
+        help = "Write the JSON summary as a bare array of sequence statistics, without the schema version."
+    )]
+    legacy_json: bool,
+
+    #[arg(
+        long = "precision",
+        value_parser = clap::value_parser!(u8).range(0..=15),
+        help = "Round all floating-point fields (e.g., ratios) in the summary to this number of decimal places (from 0 to 15) [default: full precision]."
+    )]
+    precision: Option<u8>,
+
+    #[arg(
+        long = "table",
+        default_value = "false",
+        conflicts_with = "quiet",
+        help = "Print an aligned text table (name, length, GC%, soft-masked%, hard-masked%) on stdout instead of the summary (which is still written to the summary file)."
+    )]
+    table: bool,
+
+    #[arg(
+        long = "checksum",
+        value_enum,
+        default_value = "sha256",
+        help = "The checksum(s) to compute for each sequence."
+    )]
+    checksum: ChecksumKind,
+
+    #[arg(
+        long = "checksum-uppercase",
+        default_value = "false",
+        help = "Convert all bases to upper case before computing the checksum(s), so that they do not depend on soft-masking."
+    )]
+    checksum_uppercase: bool,
+
+    #[arg(
+        long = "checksum-only",
+        default_value = "false",
+        help = "Only compute the length and checksum(s) of each sequence (e.g., to verify a download), without any other statistics or per-sequence files."
+    )]
+    checksum_only: bool,
+
+    #[arg(
+        long = "canonical-checksum",
+        default_value = "false",
+        help = "Also compute 'checksum_canonical', the SHA256 checksum of the uppercase sequence or its reverse complement (whichever sorts first), to identify sequences regardless of their strand."
+    )]
+    canonical_checksum: bool,
+
+    #[arg(
+        long = "no-checksum",
+        default_value = "false",
+        conflicts_with_all = ["checksum", "checksum_uppercase", "checksum_only", "canonical_checksum"],
+        help = "Do not compute any checksum (leaving 'checksum_sha256' empty), which saves CPU time on large genomes."
+    )]
+    no_checksum: bool,
+
+    #[arg(
+        long = "find-duplicates",
+        default_value = "false",
+        conflicts_with = "no_checksum",
+        help = "Write the names of sequences with identical SHA256 checksums (i.e., identical sequences) into 'duplicates.json', grouped by checksum."
+    )]
+    find_duplicates: bool,
+
+    #[arg(
+        long = "strict",
+        default_value = "false",
+        help = "Fail on sequences containing lowercase (soft-masked) bases, e.g., to ensure a canonical uppercase reference."
+    )]
+    strict: bool,
+
+    #[arg(
+        long = "no-soft-mask",
+        default_value = "false",
+        conflicts_with = "split_n_case",
+        help = "Count lowercase bases as non-masked, for inputs where lowercase letters do not indicate soft-masking."
+    )]
+    no_soft_mask: bool,
+
+    #[arg(
+        long = "softmask-weight",
+        default_value = "1.0",
+        help = "The weight (clamped to [0, 1]) of soft-masked bases for gc_content_weighted, in both the GC count and the length, e.g., 0.0 to exclude them."
+    )]
+    softmask_weight: f64,
+
+    #[arg(
+        long = "split-n-case",
+        default_value = "false",
+        help = "Count lowercase 'n' as soft-masked and only uppercase 'N' as hard-masked (by default, both are hard-masked)."
+    )]
+    split_n_case: bool,
+
+    #[arg(
+        long = "alignment-mode",
+        default_value = "false",
+        help = "Count '-' as alignment gap (reported as gap_dash_bases and included in alignment_length only) instead of as unexpected base."
+    )]
+    alignment_mode: bool,
+
+    #[arg(
+        long = "mask-chars",
+        default_value = DEFAULT_MASK_CHARS,
+        help = "The characters that count as hard-masked (i.e., as gaps), e.g., 'NnXx' to also treat 'X' as gap. They must not include any of 'ACGT'."
+    )]
+    mask_chars: String,
+
+    #[arg(
+        long = "gc-window",
+        help = "Store the GC content of non-overlapping windows of this size into a bedGraph file per sequence."
+    )]
+    gc_window: Option<NonZeroUsize>,
+
+    #[arg(
+        long = "gc-bins",
+        help = "Store the GC content of this number of equally sized bins per sequence into a '<sequence>.gc_profile.tsv' file (for comparing sequences of different lengths)."
+    )]
+    gc_bins: Option<NonZeroUsize>,
+
+    #[arg(
+        long = "mask-window",
+        help = "Store the fraction of soft- and hard-masked bases of non-overlapping windows of this size into a bedGraph file per sequence."
+    )]
+    mask_window: Option<NonZeroUsize>,
+
+    #[arg(
+        long = "gc-skew-track",
+        value_name = "WINDOW",
+        help = "Store the cumulative GC skew (G - C) / (G + C) of non-overlapping windows of this size into a '<sequence>.gcskew.bedgraph' file per sequence, and report the positions of its minimum and maximum (e.g., to locate the replication terminus and origin)."
+    )]
+    gc_skew_track: Option<NonZeroUsize>,
+
+    #[arg(
+        long = "emit-mask-track",
+        default_value = "false",
+        help = "Store the masking of each base as a packed bit vector (1 bit per base, 1 if soft- or hard-masked) into a '<sequence>.mask.bin' file per sequence."
+    )]
+    emit_mask_track: bool,
+
+    #[arg(
+        short = 't',
+        long = "threads",
+        help = "The number of threads for processing sequences in parallel, which also bounds the number of BED files written concurrently [default: number of cores]."
+    )]
+    threads: Option<usize>,
+
+    #[arg(
+        long = "on-duplicate",
+        value_enum,
+        default_value = "error",
+        help = "How to handle records with a name that has already been seen: fail, write their output files with a numeric suffix (e.g., 'name.2.non-masked.bed'), or merge them into a single sequence."
+    )]
+    on_duplicate: DuplicatePolicy,
+
+    #[arg(
+        long = "on-error",
+        value_enum,
+        default_value = "continue",
+        help = "How to handle records that cannot be processed (e.g., due to unexpected bases): abort without writing a summary, or continue with the other records and list the errors in 'errors.json'. Either way, the exit code is nonzero."
+    )]
+    on_error: ErrorPolicy,
+
+    #[arg(
+        long = "write-genome-file",
+        default_value = "false",
+        help = "Store the name and length of all sequences into a 'genome.txt' file (as used by bedtools and genome browsers)."
+    )]
+    write_genome_file: bool,
+
+    #[arg(
+        long = "write-fai",
+        default_value = "false",
+        help = "Store a samtools-compatible FASTA index next to the (uncompressed) FASTA file, i.e., in '<FASTA_FILE>.fai'."
+    )]
+    write_fai: bool,
+
+    #[arg(
+        long = "write-manifest",
+        default_value = "false",
+        conflicts_with = "dry_run",
+        help = "Store a 'manifest.json' file that lists all written output files with their path, type (e.g., 'summary' or 'bed'), and size, e.g., for workflow systems."
+    )]
+    write_manifest: bool,
+
+    #[arg(
+        long = "emit-fasta",
+        requires = "convert_softmask_to_n",
+        help = "Write a converted copy of the FASTA file(s) to this path (see '--convert-softmask-to-n')."
+    )]
+    emit_fasta: Option<PathBuf>,
+
+    #[arg(
+        long = "convert-softmask-to-n",
+        default_value = "false",
+        requires = "emit_fasta",
+        help = "Replace all soft-masked bases with 'N' in the FASTA file written by '--emit-fasta', for tools that only support hard-masking."
+    )]
+    convert_softmask_to_n: bool,
+
+    #[arg(
+        long = "sqlite",
+        help = "Also insert the statistics into the 'sequence_stats' table of this SQLite database (created if needed), replacing the rows with the same source file and sequence name."
+    )]
+    sqlite: Option<PathBuf>,
+
+    #[arg(
+        long = "sort",
+        value_enum,
+        default_value = "lexical",
+        help = "The order of the sequences in the summary and genome file: by name (lexical or natural, e.g., 'chr2' before 'chr10'), by descending length, or as in the input."
+    )]
+    sort: SortOrder,
+
+    #[arg(
+        long = "no-sort",
+        default_value = "false",
+        conflicts_with = "sort",
+        help = "Keep the sequences in input order (same as '--sort input')."
+    )]
+    no_sort: bool,
+
+    #[arg(
+        long = "soft-mask-lengths",
+        default_value = "false",
+        help = "Report the lengths of all soft-masked regions of each sequence as 'soft_mask_region_lengths' (which may be long lists for repeat-rich genomes)."
+    )]
+    soft_mask_lengths: bool,
+
+    #[arg(
+        long = "dinucleotides",
+        default_value = "false",
+        help = "Count all 16 dinucleotides (case-insensitive, in overlapping windows), and report the number of CpGs and their observed/expected ratio."
+    )]
+    dinucleotides: bool,
+
+    #[arg(
+        long = "tm",
+        default_value = "false",
+        help = "Estimate the melting temperature of each sequence (only meaningful for short sequences such as primers)."
+    )]
+    tm: bool,
+
+    #[arg(
+        long = "add-total",
+        default_value = "false",
+        help = "Append a 'TOTAL' record to the summary that aggregates all sequences (with empty checksums)."
+    )]
+    add_total: bool,
+
+    #[arg(
+        long = "kmer",
+        value_parser = clap::value_parser!(u8).range(1..=12),
+        help = "Store the counts of the canonical k-mers of this size (from 1 to 12) into a TSV file per sequence."
+    )]
+    kmer: Option<u8>,
+
+    #[arg(
+        long = "alphabet",
+        value_enum,
+        default_value = "dna",
+        help = "The expected alphabet of the sequences: DNA, RNA (with 'U' handled like 'T'), or auto to accept both."
+    )]
+    alphabet: Alphabet,
+}
+
+impl Cli {
+    fn validate(self: &Cli) -> Result<(), std::io::Error> {
+        let mut output_subdirs: Vec<PathBuf> = Vec::new();
+        for fasta_file in &self.fasta_files {
+            if !is_stdin(fasta_file) && !fasta_file.is_file() {
+                return Err(std::io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The input file '{:?}' is not a file.", fasta_file),
+                ));
+            }
+            let output_subdir = self.sequence_output_dir(fasta_file);
+            if output_subdirs.contains(&output_subdir) {
+                return Err(std::io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The input file '{:?}' is given more than once (or has the same file name as another one).", fasta_file),
+                ));
+            }
+            output_subdirs.push(output_subdir);
+        }
+
+        if self.softmask_weight.is_nan() {
+            return Err(std::io::Error::new(ErrorKind::InvalidInput, "The soft-mask weight must be a number."));
+        }
+
+        for expected_gc in [self.expect_gc_min, self.expect_gc_max].into_iter().flatten() {
+            if !(0.0..=1.0).contains(&expected_gc) {
+                return Err(std::io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("The expected GC content ({}) is not between 0 and 1.", expected_gc),
+                ));
+            }
+        }
+        if let (Some(expect_gc_min), Some(expect_gc_max)) = (self.expect_gc_min, self.expect_gc_max)
+            && expect_gc_min > expect_gc_max
+        {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("The minimum GC content ({}) is greater than the maximum ({}).", expect_gc_min, expect_gc_max),
+            ));
+        }
+        if self.expect_gc_per_sequence && self.expect_gc_min.is_none() && self.expect_gc_max.is_none() {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Checking the GC content per sequence requires '--expect-gc-min' or '--expect-gc-max'.",
+            ));
+        }
+
+        if let (Some(min_length), Some(max_length)) = (self.min_length, self.max_length)
+            && min_length > max_length
+        {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("The minimum length ({}) is greater than the maximum length ({}).", min_length, max_length),
+            ));
+        }
+
+        if self.mask_chars.is_empty()
+            || !self.mask_chars.is_ascii()
+            || self.mask_chars.contains(['A', 'C', 'G', 'T', 'a', 'c', 'g', 't'])
+        {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("The mask characters '{}' must be ASCII characters other than 'ACGT' (in either case).", self.mask_chars),
+            ));
+        }
+
+        if self.find_duplicates && !matches!(self.checksum, ChecksumKind::Sha256 | ChecksumKind::Both) {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Duplicate sequences can only be found with SHA256 checksums (see '--checksum').",
+            ));
+        }
+
+        if self.emit_mask_track && self.on_duplicate == DuplicatePolicy::Merge {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Mask tracks cannot be appended to, so '--emit-mask-track' does not support '--on-duplicate merge'.",
+            ));
+        }
+
+        if let Some(regions) = &self.regions
+            && !regions.is_file()
+        {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("The regions file '{:?}' is not a file.", regions),
+            ));
+        }
+        if let Some(resume_from) = &self.resume_from
+            && !resume_from.is_file()
+        {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("The previous summary '{:?}' is not a file.", resume_from),
+            ));
+        }
+
+        if let Some(group_by_regex) = &self.group_by_regex
+            && let Err(e) = group_assembly_statistics(&[], group_by_regex)
+        {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} (the group regex needs exactly one capture group).", e),
+            ));
+        }
+
+        if self.write_fai && self.reads_from_stdin() {
+            Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "A FASTA index cannot be written when reading from stdin.",
+            ))
+        } else if self.emit_fasta.is_some() && self.reads_from_stdin() {
+            Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "A converted FASTA file cannot be written when reading from stdin.",
+            ))
+        } else if self.output_dir.is_file() {
+            Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("The output directory '{:?}' is a file.", self.output_dir),
+            ))
+        } else if let Some(bed_dir) = self.bed_dir.as_ref().filter(|bed_dir| bed_dir.is_file()) {
+            Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("The BED directory '{:?}' is a file.", bed_dir),
+            ))
+        } else {
+            for dir in [Some(&self.output_dir), self.bed_dir.as_ref()].into_iter().flatten() {
+                if !dir.exists() && !self.dry_run {
+                    fs::create_dir_all(dir)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn reads_from_stdin(&self) -> bool {
+        self.fasta_files.iter().any(|fasta_file| is_stdin(fasta_file))
+    }
+
+    /// The path of an output file in the output directory, with the prefix prepended to its name.
+    fn output_path(&self, file_name: &str) -> PathBuf {
+        self.output_dir.join(prefixed_file_name(self.prefix.as_deref().unwrap_or_default(), file_name))
+    }
+
+    /// The directory for the per-sequence output files of the FASTA file, which is a subdirectory named after the file
+    /// if there are multiple FASTA files.
+    fn sequence_output_dir(&self, fasta_file: &Path) -> PathBuf {
+        self.file_subdir(&self.output_dir, fasta_file)
+    }
+
+    /// The directory for the per-sequence BED files of the FASTA file (if different from [Cli::sequence_output_dir]),
+    /// with a subdirectory per file like the latter.
+    fn sequence_bed_dir(&self, fasta_file: &Path) -> Option<PathBuf> {
+        self.bed_dir.as_ref().map(|bed_dir| self.file_subdir(bed_dir, fasta_file))
+    }
+
+    fn file_subdir(&self, dir: &Path, fasta_file: &Path) -> PathBuf {
+        if self.fasta_files.len() > 1 {
+            let file_name = fasta_file.file_name().unwrap_or(fasta_file.as_os_str()).to_string_lossy();
+            dir.join(sanitize_filename(&file_name))
+        } else {
+            dir.to_path_buf()
+        }
+    }
+
+    fn sort_order(&self) -> SortOrder {
+        if self.no_sort { SortOrder::Input } else { self.sort }
+    }
+
+    fn stats_options(&self) -> StatsOptions {
+        StatsOptions {
+            output_dir: None,
+            bed_dir: None,
+            match_regex: Some(self.sequence_match_regex.clone()),
+            ignore_iupac: self.ignore_iupac,
+            iupac_aware: self.iupac_aware,
+            report_unexpected: self.report_unexpected,
+            checksum_kind: self.checksum,
+            checksum_uppercase: self.checksum_uppercase,
+            checksum_only: self.checksum_only,
+            canonical_checksum: self.canonical_checksum,
+            no_checksum: self.no_checksum,
+            strict: self.strict,
+            no_bed_output: self.no_bed_output || self.combined_bed || self.only_summary,
+            gc_window: self.gc_window.map(NonZeroUsize::get),
+            gc_bins: self.gc_bins.map(NonZeroUsize::get),
+            mask_window: self.mask_window.map(NonZeroUsize::get),
+            gc_skew_window: self.gc_skew_track.map(NonZeroUsize::get),
+            mask_track: self.emit_mask_track,
+            on_duplicate: self.on_duplicate,
+            soft_mask_region_lengths: self.soft_mask_lengths,
+            dinucleotides: self.dinucleotides,
+            tm: self.tm,
+            position_frequency_matrix: self.pfm.then(Default::default),
+            record_timings: self.record_timings,
+            kmer_size: self.kmer.map(usize::from),
+            alphabet: self.alphabet,
+            bed_columns: self.bed_columns,
+            compress_bed: self.compress_bed,
+            merge_mask_bed: self.merge_mask_bed,
+            no_soft_mask: self.no_soft_mask,
+            softmask_weight: Some(self.softmask_weight),
+            split_n_case: self.split_n_case,
+            alignment_mode: self.alignment_mode,
+            mask_chars: Some(self.mask_chars.clone()),
+            exclude_regex: self.sequence_exclude_regex.clone(),
+            case_insensitive_names: self.case_insensitive_names,
+            file_prefix: self.prefix.clone().unwrap_or_default(),
+            min_length: self.min_length,
+            max_length: self.max_length,
+            regions: None,
+            per_region: self.per_region,
+            previous_statistics: None,
+        }
+    }
+}
+
+fn is_stdin(fasta_file: &Path) -> bool {
+    fasta_file.as_os_str() == "-"
+}
+
+/// Expands input paths containing `*` or `?` (e.g., quoted on the shell) into the matching files, in alphabetical
+/// order. All other paths are kept as they are.
+fn expand_glob_patterns(fasta_files: &[PathBuf]) -> Result<Vec<PathBuf>, std::io::Error> {
+    let mut expanded_files = Vec::new();
+    for fasta_file in fasta_files {
+        let pattern = match fasta_file.to_str() {
+            Some(pattern) if pattern.contains(['*', '?']) => pattern,
+            _ => {
+                expanded_files.push(fasta_file.clone());
+                continue;
+            }
+        };
+        let paths = glob::glob(pattern).map_err(|error| {
+            std::io::Error::new(ErrorKind::InvalidInput, format!("Invalid pattern '{}': {}", pattern, error))
+        })?;
+        let mut matching_files = Vec::new();
+        for path in paths {
+            let path = path?;
+            if path.is_file() {
+                matching_files.push(path);
+            }
+        }
+        if matching_files.is_empty() {
+            return Err(std::io::Error::new(
+                ErrorKind::NotFound,
+                format!("No input files match the pattern '{}'.", pattern),
+            ));
+        }
+        expanded_files.extend(matching_files);
+    }
+    Ok(expanded_files)
+}
+
+fn open_fasta_input(fasta_file: &Path) -> Result<Box<dyn BufRead + Send>, std::io::Error> {
+    if is_stdin(fasta_file) {
+        // Without a file name, compressed input is only recognized by peeking at its first bytes.
+        decompress_if_gzipped(BufReader::new(std::io::stdin()))
+    } else {
+        open_fasta_file(fasta_file)
+    }
+}
+
+/// Fails with a helpful error if the (decompressed) input starts with `@`, i.e., looks like FASTQ, which the FASTA
+/// reader would misparse.
+fn ensure_not_fastq<R: BufRead>(mut reader: R, fasta_file: &Path) -> Result<R, std::io::Error> {
+    if reader.fill_buf()?.starts_with(b"@") {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            format!("The input {:?} looks like FASTQ (it starts with '@'), but only FASTA is supported.", fasta_file),
+        ));
+    }
+    Ok(reader)
+}
+
+/// The first two bytes of any gzip (and thus also bgzip) stream.
+const GZIP_MAGIC_BYTES: [u8; 2] = [0x1f, 0x8b];
+
+/// Whether the gzip header announces a bgzip block, i.e., has an extra field (`FEXTRA` flag) whose first subfield
+/// has the identifier `BC`.
+fn is_bgzip_header(header: &[u8]) -> bool {
+    header.len() >= 14 && header.starts_with(&GZIP_MAGIC_BYTES) && header[3] & 0x04 != 0 && &header[12..14] == b"BC"
+}
+
+/// Wraps the reader into a bgzip or gzip decoder if its content starts with the (b)gzip magic bytes.
+fn decompress_if_gzipped<R: BufRead + Send + 'static>(mut reader: R) -> Result<Box<dyn BufRead + Send>, std::io::Error> {
+    let header = reader.fill_buf()?;
+    if is_bgzip_header(header) {
+        Ok(Box::new(bgzf::io::Reader::new(reader)))
+    } else if header.starts_with(&GZIP_MAGIC_BYTES) {
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+fn open_fasta_file(fasta_file: &Path) -> Result<Box<dyn BufRead + Send>, std::io::Error> {
+    decompress_if_gzipped(BufReader::new(File::open(fasta_file)?))
+}
+
+/// Indexes the FASTA file in a separate pass and writes the index to `<fasta_file>.fai`, returning its path.
+fn write_fasta_index(fasta_file: &Path) -> Result<PathBuf, std::io::Error> {
+    if BufReader::new(File::open(fasta_file)?).fill_buf()?.starts_with(&GZIP_MAGIC_BYTES) {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Cannot index the compressed FASTA file '{}'.", fasta_file.display()),
+        ));
+    }
+    let index = fasta::fs::index(fasta_file)?;
+    let mut index_path = fasta_file.as_os_str().to_owned();
+    index_path.push(".fai");
+    let index_path = PathBuf::from(index_path);
+    fasta::fai::fs::write(&index_path, &index)?;
+    Ok(index_path)
+}
+
+/// Writes the content of all FASTA files into a single (uncompressed) FASTA file, with soft-masked bases replaced by
+/// `N`, see [convert_soft_mask_to_n]. The input files are read a second time, as they may be compressed.
+fn write_converted_fasta(fasta_files: &[PathBuf], output_path: &Path) -> Result<(), std::io::Error> {
+    let mut writer = BufWriter::new(File::create(output_path)?);
+    for fasta_file in fasta_files {
+        convert_soft_mask_to_n(open_fasta_file(fasta_file)?, &mut writer)?;
+    }
+    writer.flush()
+}
+
+/// Inserts the statistics into the `sequence_stats` table of an SQLite database (created if needed), with a column per
+/// field and `(source_file, sequence_name)` as primary key. Existing rows with the same key are replaced, and columns
+/// of fields that the table does not have yet are added, so that the results of several runs can be collected in one
+/// database. All rows are inserted in a single transaction.
+fn write_sqlite(path: &Path, sequence_statistics: &[SequenceStatistics]) -> Result<(), Box<dyn Error>> {
+    let rows = statistics_rows(sequence_statistics)?;
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let columns = row_columns(&rows);
+    let column_definitions: Vec<String> = columns
+        .iter()
+        .map(|column| format!("\"{}\" {}", column, sqlite_column_type(&rows, column)))
+        .collect();
+
+    let mut connection = rusqlite::Connection::open(path)?;
+    let transaction = connection.transaction()?;
+    transaction.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS sequence_stats ({}, PRIMARY KEY (source_file, sequence_name))",
+            column_definitions.join(", ")
+        ),
+        [],
+    )?;
+    let existing_columns: Vec<String> = transaction
+        .prepare("SELECT name FROM pragma_table_info('sequence_stats')")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    for (column, definition) in columns.iter().zip(&column_definitions) {
+        if !existing_columns.contains(column) {
+            transaction.execute(&format!("ALTER TABLE sequence_stats ADD COLUMN {}", definition), [])?;
+        }
+    }
+
+    let column_names: Vec<String> = columns.iter().map(|column| format!("\"{}\"", column)).collect();
+    let mut statement = transaction.prepare(&format!(
+        "INSERT OR REPLACE INTO sequence_stats ({}) VALUES ({})",
+        column_names.join(", "),
+        vec!["?"; columns.len()].join(", ")
+    ))?;
+    for row in &rows {
+        statement.execute(rusqlite::params_from_iter(columns.iter().map(|column| sqlite_value(row.get(*column)))))?;
+    }
+    drop(statement);
+    transaction.commit()?;
+    Ok(())
+}
+
+/// The SQLite type of a column, given by its first non-null value (lists and maps are stored as JSON text).
+fn sqlite_column_type(rows: &[serde_json::Map<String, serde_json::Value>], column: &str) -> &'static str {
+    let value = rows.iter().filter_map(|row| row.get(column)).find(|value| !value.is_null());
+    match value {
+        Some(serde_json::Value::Number(number)) if number.is_f64() => "REAL",
+        Some(serde_json::Value::Number(_) | serde_json::Value::Bool(_)) => "INTEGER",
+        _ => "TEXT",
+    }
+}
+
+fn sqlite_value(value: Option<&serde_json::Value>) -> rusqlite::types::Value {
+    use rusqlite::types::Value as SqliteValue;
+    match value {
+        None | Some(serde_json::Value::Null) => SqliteValue::Null,
+        Some(serde_json::Value::Bool(value)) => SqliteValue::Integer(*value as i64),
+        Some(serde_json::Value::Number(number)) => match number.as_i64() {
+            Some(integer) => SqliteValue::Integer(integer),
+            None => SqliteValue::Real(number.as_f64().unwrap_or(f64::NAN)),
+        },
+        Some(serde_json::Value::String(string)) => SqliteValue::Text(string.clone()),
+        Some(value) => SqliteValue::Text(value.to_string()),
+    }
+}
+
+/// Creates a progress bar that counts the processed records on stderr, or a hidden one (for which all updates are
+/// no-ops) if stderr is not a terminal or quiet mode is enabled.
+fn create_progress_bar(quiet: bool) -> ProgressBar {
+    if quiet || !std::io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    let progress_bar = ProgressBar::new_spinner().with_style(
+        ProgressStyle::with_template("{spinner} Processed {human_pos} record(s) [{elapsed_precise}]")
+            .expect("Invalid progress bar template."),
+    );
+    progress_bar.enable_steady_tick(std::time::Duration::from_millis(200));
+    progress_bar
+}
+
+/// Processes the records in parallel while they are read, so that only a few records are held in memory at a time.
+/// The occurrences of each name are counted in input order. When merging duplicates, records with a name that has
+/// already been seen are kept back and processed one after another at the end, so that their regions are appended to
+/// the output files of the first record with that name. The results are returned in input order. With
+/// [ErrorPolicy::Abort], no further records are read once a record fails (records that are already being processed are
+/// still finished). At most `max_records` records are read (if set), without reading the rest of the input. The time
+/// spent on reading (and parsing) the records is added to `read_time`.
+fn process_fasta_records<R, F>(
+    mut reader: fasta::io::Reader<R>,
+    process_record: F,
+    on_duplicate: DuplicatePolicy,
+    on_error: ErrorPolicy,
+    max_records: Option<usize>,
+    read_time: &mut Duration,
+) -> Result<Vec<Result<Vec<SequenceStatistics>, ProcessError>>, std::io::Error>
+where
+    R: BufRead + Send,
+    F: Fn(&FastaRecord, usize) -> Result<Vec<SequenceStatistics>, ProcessError> + Send + Sync,
+{
+    let mut name_occurrences: HashMap<Vec<u8>, usize> = HashMap::new();
+    let deferred_records: Mutex<Vec<(usize, FastaRecord, usize)>> = Mutex::new(Vec::new());
+    let aborted = AtomicBool::new(false);
+    let process = |record: &FastaRecord, occurrence| {
+        let result = process_record(record, occurrence);
+        if result.is_err() && on_error == ErrorPolicy::Abort {
+            aborted.store(true, Ordering::SeqCst);
+        }
+        result
+    };
+    let mut records = reader.records();
+    let timed_records = std::iter::from_fn(|| {
+        if aborted.load(Ordering::SeqCst) {
+            return None;
+        }
+        let read_start = Instant::now();
+        let record = records.next();
+        *read_time += read_start.elapsed();
+        record
+    });
+    let mut results: Vec<(usize, Result<Vec<SequenceStatistics>, ProcessError>)> = timed_records
+        .take(max_records.unwrap_or(usize::MAX))
+        .enumerate()
+        .map(|(index, record)| {
+            record.map(|record| {
+                let occurrence = name_occurrences.entry(record.name().to_vec()).or_insert(0);
+                *occurrence += 1;
+                (index, record, *occurrence)
+            })
+        })
+        .par_bridge()
+        .filter_map(|record| match record {
+            Ok((index, record, occurrence)) if on_duplicate == DuplicatePolicy::Merge && occurrence > 1 => {
+                deferred_records.lock().unwrap().push((index, record, occurrence));
+                None
+            }
+            Ok((index, record, occurrence)) => Some(Ok((index, process(&record, occurrence)))),
+            Err(error) => Some(Err(error)),
+        })
+        .collect::<Result<_, _>>()?;
+
+    let mut deferred_records = deferred_records.into_inner().unwrap();
+    deferred_records.sort_unstable_by_key(|(index, _, _)| *index);
+    for (index, record, occurrence) in &deferred_records {
+        if aborted.load(Ordering::SeqCst) {
+            break;
+        }
+        results.push((*index, process(record, *occurrence)));
+    }
+    results.sort_unstable_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+/// Merges the statistics of all records with the same name (from the same source file) into the first of them, see
+/// [merge_sequence_statistics].
+fn merge_duplicates(sequence_statistics: Vec<SequenceStatistics>) -> Vec<SequenceStatistics> {
+    let mut merged_statistics: Vec<SequenceStatistics> = Vec::new();
+    let mut name_indices: HashMap<(String, String), usize> = HashMap::new();
+    for stats in sequence_statistics {
+        let key = (stats.source_file.clone(), stats.sequence_name.clone());
+        match name_indices.get(&key) {
+            Some(&index) => merged_statistics[index] = merge_sequence_statistics(&merged_statistics[index], &stats),
+            None => {
+                name_indices.insert(key, merged_statistics.len());
+                merged_statistics.push(stats);
+            }
+        }
+    }
+    merged_statistics
+}
+
+/// Sorts the statistics in a stable way, so that records that compare equal (e.g., overlapping regions with
+/// [StatsOptions::per_region]) keep their input order and the output is the same in every run.
+fn sort_statistics(sequence_statistics: &mut [SequenceStatistics], sort_order: SortOrder) {
+    match sort_order {
+        SortOrder::Lexical => sequence_statistics.sort_by(|a, b| {
+            a.sequence_name
+                .cmp(&b.sequence_name)
+                .then_with(|| a.source_file.cmp(&b.source_file))
+        }),
+        SortOrder::Natural => sequence_statistics.sort_by(|a, b| {
+            natural_cmp(&a.sequence_name, &b.sequence_name).then_with(|| a.source_file.cmp(&b.source_file))
+        }),
+        SortOrder::Length => sequence_statistics.sort_by(|a, b| {
+            b.sequence_length
+                .cmp(&a.sequence_length)
+                .then_with(|| a.sequence_name.cmp(&b.sequence_name))
+                .then_with(|| a.source_file.cmp(&b.source_file))
+        }),
+        SortOrder::Input => {}
+    }
+}
+
+fn format_summary(
+    sequence_statistics: &[SequenceStatistics],
+    format: OutputFormat,
+    legacy_json: bool,
+) -> Result<String, Box<dyn Error>> {
+    let delimiter = match format {
+        OutputFormat::Json if legacy_json => return Ok(serde_json::to_string_pretty(sequence_statistics)?),
+        OutputFormat::Json => return Ok(serde_json::to_string_pretty(&SummaryReport::new(sequence_statistics))?),
+        OutputFormat::Ndjson => {
+            let mut output = String::new();
+            for stats in sequence_statistics {
+                output.push_str(&serde_json::to_string(stats)?);
+                output.push('\n');
+            }
+            return Ok(output);
+        }
+        OutputFormat::Tsv => b'\t',
+        OutputFormat::Csv => b',',
+    };
+
+    // Columns are collected via JSON, as the csv crate cannot serialize fields that hold lists or maps.
+    let rows = statistics_rows(sequence_statistics)?;
+    let columns = row_columns(&rows);
+    let mut writer = csv::WriterBuilder::new().delimiter(delimiter).from_writer(Vec::new());
+    writer.write_record(&columns)?;
+    for row in &rows {
+        writer.write_record(columns.iter().map(|column| format_table_cell(row.get(*column))))?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// Serializes the statistics of each sequence into a JSON object, whose keys are the (serialized) field names.
+fn statistics_rows(
+    sequence_statistics: &[SequenceStatistics],
+) -> Result<Vec<serde_json::Map<String, serde_json::Value>>, serde_json::Error> {
+    sequence_statistics
+        .iter()
+        .map(|stats| match serde_json::to_value(stats) {
+            Ok(serde_json::Value::Object(row)) => Ok(row),
+            Ok(_) => unreachable!("Sequence statistics are always serialized as JSON objects."),
+            Err(error) => Err(error),
+        })
+        .collect()
+}
+
+/// The keys of all rows in the order in which they first appear, as optional fields may be missing in some rows.
+fn row_columns(rows: &[serde_json::Map<String, serde_json::Value>]) -> Vec<&String> {
+    let mut columns: Vec<&String> = Vec::new();
+    for column in rows.iter().flat_map(|row| row.keys()) {
+        if !columns.contains(&column) {
+            columns.push(column);
+        }
+    }
+    columns
+}
+
+fn format_table_cell(value: Option<&serde_json::Value>) -> String {
+    match value {
+        None | Some(serde_json::Value::Null) => "".to_string(),
+        Some(serde_json::Value::String(string)) => string.clone(),
+        Some(serde_json::Value::Array(values)) => values
+            .iter()
+            .map(|value| format_table_cell(Some(value)))
+            .collect::<Vec<String>>()
+            .join(","),
+        Some(value) => value.to_string(),
+    }
+}
+
+/// Formats the Nx curve as two tab-separated columns `x_percent` and `nx_length`, with a header line.
+fn format_nx_curve(assembly_statistics: &AssemblyStatistics) -> String {
+    let mut output = "x_percent\tnx_length\n".to_string();
+    for (x, nx) in &assembly_statistics.nx_curve {
+        output.push_str(&format!("{}\t{}\n", x, nx));
+    }
+    output
+}
+
+/// Formats the position frequency matrix as tab-separated columns `position` (1-based), `A`, `C`, `G`, and `T`, with a
+/// header line.
+fn format_position_frequency_matrix(matrix: &[[usize; 4]]) -> String {
+    let mut output = "position\tA\tC\tG\tT\n".to_string();
+    for (position, [a, c, g, t]) in matrix.iter().enumerate() {
+        output.push_str(&format!("{}\t{}\t{}\t{}\t{}\n", position + 1, a, c, g, t));
+    }
+    output
+}
+
+/// Formats the sequence names and lengths as genome file, i.e., with two tab-separated columns.
+fn format_genome_file(sequence_statistics: &[SequenceStatistics]) -> String {
+    sequence_statistics
+        .iter()
+        .map(|stats| format!("{}\t{}\n", stats.sequence_name, stats.sequence_length))
+        .collect()
+}
+
+/// Lists the unexpected bases of all sequences as `sequence_name`, (0-based) `position`, and `base`, with a final
+/// comment line if more than [MAX_REPORTED_UNEXPECTED_BASES] were found.
+fn format_unexpected_bases(sequence_statistics: &[SequenceStatistics]) -> String {
+    let mut output = "sequence_name\tposition\tbase\n".to_string();
+    let unexpected_bases = sequence_statistics.iter().flat_map(|stats| {
+        stats.unexpected_bases.iter().map(move |(position, base)| (&stats.sequence_name, position, base))
+    });
+    for (name, position, base) in unexpected_bases.take(MAX_REPORTED_UNEXPECTED_BASES) {
+        output.push_str(&format!("{}\t{}\t{}\n", name, position, base.escape_ascii()));
+    }
+    let total: usize = sequence_statistics.iter().map(|stats| stats.iupac_ambiguous_bases + stats.other_bases).sum();
+    if total > MAX_REPORTED_UNEXPECTED_BASES {
+        output.push_str(&format!(
+            "# Truncated: only the first {} of {} unexpected bases are listed.\n",
+            MAX_REPORTED_UNEXPECTED_BASES, total
+        ));
+    }
+    output
+}
+
+/// Lists a warning for each empty sequence and each sequence shorter than `min_length`.
+fn short_sequence_warnings(sequence_statistics: &[SequenceStatistics], min_length: usize) -> Vec<String> {
+    sequence_statistics
+        .iter()
+        .filter_map(|stats| match stats.sequence_length {
+            0 => Some(format!("Sequence '{}' is empty.", stats.sequence_name)),
+            length if length < min_length => Some(format!(
+                "Sequence '{}' is shorter than {} bp ({} bp).",
+                stats.sequence_name, min_length, length
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Lists the sequences whose GC content is outside the expected range, or only the total of all sequences (named
+/// [TOTAL_SEQUENCE_NAME]) unless `per_sequence` is set. Empty sequences are not checked.
+fn gc_content_violations(
+    sequence_statistics: &[SequenceStatistics],
+    min: Option<f64>,
+    max: Option<f64>,
+    per_sequence: bool,
+) -> Vec<String> {
+    if min.is_none() && max.is_none() {
+        return Vec::new();
+    }
+    let total: Vec<SequenceStatistics>;
+    let checked_statistics = if per_sequence {
+        sequence_statistics
+    } else {
+        total = total_sequence_statistics(sequence_statistics).into_iter().collect();
+        &total
+    };
+    checked_statistics
+        .iter()
+        .filter(|stats| stats.sequence_length > 0)
+        .filter(|stats| min.is_some_and(|min| stats.gc_content < min) || max.is_some_and(|max| stats.gc_content > max))
+        .map(|stats| {
+            format!(
+                "The GC content of '{}' ({:.4}) is outside the expected range [{}, {}].",
+                stats.sequence_name,
+                stats.gc_content,
+                min.unwrap_or(0.0),
+                max.unwrap_or(1.0)
+            )
+        })
+        .collect()
+}
+
+/// Formats the main statistics of each sequence as a human-readable table, with percentages instead of ratios.
+fn format_text_table(sequence_statistics: &[SequenceStatistics]) -> String {
+    let percent = |ratio: f64| format!("{:.2}", ratio * 100.0);
+    let mut rows = vec![["name", "length", "GC%", "soft%", "hard%"].map(String::from).to_vec()];
+    rows.extend(sequence_statistics.iter().map(|stats| {
+        vec![
+            stats.sequence_name.clone(),
+            stats.sequence_length.to_string(),
+            percent(stats.gc_content),
+            percent(stats.soft_masked_ratio),
+            percent(stats.hard_masked_ratio),
+        ]
+    }));
+    align_columns(&rows)
+}
+
+/// Pads the cells of each column to the same width and separates them by two spaces. The first column is aligned to
+/// the left and all others (i.e., numbers) to the right.
+fn align_columns(rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = Vec::new();
+    for row in rows {
+        widths.resize(widths.len().max(row.len()), 0);
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut output = String::new();
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(index, (cell, width))| if index == 0 { format!("{cell:<width$}") } else { format!("{cell:>width$}") })
+            .collect();
+        output.push_str(cells.join("  ").trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = Cli::parse();
+    // The message is returned instead of the error, as the latter would be printed with its debug representation.
+    args.fasta_files = expand_glob_patterns(&args.fasta_files).map_err(|error| error.to_string())?;
+    env_logger::Builder::new().filter_level(args.log_level.level_filter()).init();
+    args.validate().expect("Failed to validate CLI arguments");
+    run(&args)
+}
+
+/// Processes all FASTA files and writes the outputs as configured by the (validated) arguments.
+fn run(args: &Cli) -> Result<(), Box<dyn Error>> {
+    let combined_bed_path = (args.combined_bed && !args.no_bed_output && !args.checksum_only && !args.dry_run)
+        .then(|| args.output_path(if args.compress_bed { "masking.bed.gz" } else { "masking.bed" }));
+    let combined_bed_output: Option<Mutex<Box<dyn Write + Send>>> = match &combined_bed_path {
+        Some(path) => {
+            let file = BufWriter::new(File::create(path)?);
+            let output = if args.compress_bed {
+                Box::new(GzEncoder::new(file, Compression::default())) as Box<dyn Write + Send>
+            } else {
+                Box::new(file)
+            };
+            Some(Mutex::new(output))
+        }
+        None => None,
+    };
+
+    let mut stats_options = args.stats_options();
+    if let Some(regions) = &args.regions {
+        stats_options.regions = Some(Arc::new(read_regions(BufReader::new(File::open(regions)?))?));
+    }
+    if let Some(resume_from) = &args.resume_from {
+        // The first statistics of each name are reused, like the first record of each name is processed.
+        let previous_statistics = read_summary(decompress_if_gzipped(BufReader::new(File::open(resume_from)?))?)?;
+        stats_options.previous_statistics = Some(Arc::new(
+            previous_statistics.into_iter().rev().map(|stats| (stats.sequence_name.clone(), stats)).collect(),
+        ));
+    }
+    let progress_bar = create_progress_bar(args.quiet);
+    let mut timings = Timings::default();
+    let processing_time = Mutex::new(Duration::ZERO);
+    let mut process = || {
+        let mut results: Vec<Result<Vec<SequenceStatistics>, RecordError>> = Vec::new();
+        for fasta_file in &args.fasta_files {
+            if args.max_records.is_some_and(|max_records| results.len() >= max_records.get()) {
+                break;
+            }
+            let reader = open_fasta_input(fasta_file)
+                .and_then(|reader| ensure_not_fastq(reader, fasta_file))
+                .map(fasta::io::Reader::new)?;
+            // Without an output directory, no per-sequence files are written.
+            let output_dir = (!args.dry_run && !args.only_summary && args.regions.is_none())
+                .then(|| args.sequence_output_dir(fasta_file));
+            let bed_dir = output_dir.as_ref().and_then(|_| args.sequence_bed_dir(fasta_file));
+            for dir in [&output_dir, &bed_dir].into_iter().flatten() {
+                fs::create_dir_all(dir)?;
+            }
+            let file_options = StatsOptions { output_dir, bed_dir, ..stats_options.clone() };
+            let process_record = process_fasta(
+                combined_bed_output.as_ref().map(|output| output as &Mutex<dyn Write + Send>),
+                &file_options,
+            );
+            let file_results = process_fasta_records(
+                reader,
+                |record, occurrence| {
+                    let processing_start = Instant::now();
+                    let result = process_record(record, occurrence);
+                    *processing_time.lock().unwrap() += processing_start.elapsed();
+                    progress_bar.inc(1);
+                    result
+                },
+                args.on_duplicate,
+                args.on_error,
+                args.max_records.map(|max_records| max_records.get() - results.len()),
+                &mut timings.reading,
+            )?;
+            let source_file = fasta_file.display().to_string();
+            results.extend(file_results.into_iter().map(|result| {
+                result
+                    .map(|stats| {
+                        stats
+                            .into_iter()
+                            .map(|stats| SequenceStatistics {
+                                source_file: source_file.clone(),
+                                ..stats
+                            })
+                            .collect()
+                    })
+                    .map_err(|error| RecordError { source_file: source_file.clone(), error: error.to_string() })
+            }));
+            if args.on_error == ErrorPolicy::Abort && results.iter().any(Result::is_err) {
+                break;
+            }
+        }
+        Ok::<_, std::io::Error>(results)
+    };
+    let results = match args.threads {
+        Some(threads) if threads > 0 => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()?
+            .install(process)?,
+        _ => process()?,
+    };
+    progress_bar.finish_and_clear();
+    timings.processing = processing_time.into_inner().unwrap();
+    let flush_start = Instant::now();
+    if let Some(output) = combined_bed_output {
+        output.into_inner()?.flush()?;
+    }
+    timings.bed_writing = flush_start.elapsed();
+
+    let mut sequence_statistics: Vec<SequenceStatistics> = Vec::new();
+    let mut errors: Vec<RecordError> = Vec::new();
+    for result in results {
+        match result {
+            Ok(stats) => {
+                timings.bed_writing += stats.iter().map(|stats| stats.bed_writing_time).sum::<Duration>();
+                sequence_statistics.extend(stats)
+            }
+            Err(error) => errors.push(error),
+        }
+    }
+    for error in &errors {
+        eprintln!("Error: {}", error.error);
+    }
+    if args.on_error == ErrorPolicy::Abort && !errors.is_empty() {
+        return Err(format!("Aborted after failing to process {} record(s).", errors.len()).into());
+    }
+    if args.on_duplicate == DuplicatePolicy::Merge {
+        sequence_statistics = merge_duplicates(sequence_statistics);
+    }
+    sort_statistics(&mut sequence_statistics, args.sort_order());
+    if !args.quiet {
+        for warning in short_sequence_warnings(&sequence_statistics, args.warn_short) {
+            eprintln!("Warning: {}", warning);
+        }
+    }
+
+    if args.dry_run {
+        eprintln!("Validated {} sequence(s).", sequence_statistics.len());
+    } else {
+        let summary_start = Instant::now();
+        let position_frequency_matrix = stats_options.position_frequency_matrix.as_deref();
+        let mut output_files = write_summary_outputs(args, &sequence_statistics, position_frequency_matrix)?;
+        if !errors.is_empty() {
+            let errors_path = args.output_path("errors.json");
+            fs::write(&errors_path, serde_json::to_string_pretty(&errors)?)?;
+            output_files.push((errors_path, "errors"));
+        }
+        if args.write_manifest {
+            output_files.extend(combined_bed_path.map(|path| (path, "bed")));
+            output_files.extend(sequence_statistics.iter().flat_map(|stats| stats.output_files.iter().cloned()));
+            let manifest = manifest_entries(&output_files)?;
+            fs::write(args.output_path("manifest.json"), serde_json::to_string_pretty(&manifest)?)?;
+        }
+        timings.summary = summary_start.elapsed();
+    }
+    if args.timings {
+        eprint!("{}", timings);
+    }
+
+    let gc_violations = gc_content_violations(
+        &sequence_statistics,
+        args.expect_gc_min,
+        args.expect_gc_max,
+        args.expect_gc_per_sequence,
+    );
+    for violation in &gc_violations {
+        eprintln!("Error: {}", violation);
+    }
+    if !gc_violations.is_empty() {
+        let message = format!("The GC content of {} sequence(s) is outside the expected range.", gc_violations.len());
+        return Err(message.into());
+    }
+
+    if !errors.is_empty() {
+        return Err(format!("Failed to process {} record(s).", errors.len()).into());
+    }
+    Ok(())
+}
+
+/// A record that could not be processed, as listed in `errors.json`.
+#[derive(Debug, serde::Serialize)]
+struct RecordError {
+    source_file: String,
+    error: String,
+}
+
+/// Wall-clock times of the processing phases, as printed with `--timings`.
+#[derive(Debug, Default)]
+struct Timings {
+    reading: Duration,
+    /// Summed up over all threads, so it may exceed the overall time. Includes [Timings::bed_writing].
+    processing: Duration,
+    /// Summed up over all threads, like [Timings::processing].
+    bed_writing: Duration,
+    summary: Duration,
+}
+
+impl std::fmt::Display for Timings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Reading records:     {:.3?}", self.reading)?;
+        writeln!(f, "Processing records:  {:.3?} (summed up over all threads)", self.processing)?;
+        writeln!(f, "  writing BED files: {:.3?}", self.bed_writing)?;
+        writeln!(f, "Writing summary:     {:.3?}", self.summary)
+    }
+}
+
+/// An output file as listed in `manifest.json`.
+#[derive(Debug, PartialEq, serde::Serialize)]
+struct ManifestEntry {
+    path: String,
+    #[serde(rename = "type")]
+    file_type: &'static str,
+    size: u64,
+}
+
+/// Lists the output files with their current size, in the given order but without duplicates (e.g., the BED files of
+/// merged records).
+fn manifest_entries(output_files: &[(PathBuf, &'static str)]) -> Result<Vec<ManifestEntry>, std::io::Error> {
+    let mut entries: Vec<ManifestEntry> = Vec::new();
+    let mut listed_paths: HashSet<&Path> = HashSet::new();
+    for (path, file_type) in output_files {
+        if listed_paths.insert(path) {
+            entries.push(ManifestEntry { path: path.display().to_string(), file_type, size: fs::metadata(path)?.len() });
+        }
+    }
+    Ok(entries)
+}
+
+/// Prints the summary and writes the summary files (summary, genome file, FASTA index, and assembly statistics, also
+/// per group), as well as the position frequency matrix (if any). Returns the paths of the written files along with
+/// their type, see [ManifestEntry].
+fn write_summary_outputs(
+    args: &Cli,
+    sequence_statistics: &[SequenceStatistics],
+    position_frequency_matrix: Option<&Mutex<PositionFrequencyMatrix>>,
+) -> Result<Vec<(PathBuf, &'static str)>, Box<dyn Error>> {
+    let mut output_files: Vec<(PathBuf, &'static str)> = Vec::new();
+    // The statistics are only copied if they need to be extended or rounded.
+    let mut summary_statistics = Cow::Borrowed(sequence_statistics);
+    if args.add_total
+        && let Some(total) = total_sequence_statistics(sequence_statistics)
+    {
+        summary_statistics.to_mut().push(total);
+    }
+    if let Some(precision) = args.precision {
+        for stats in summary_statistics.to_mut() {
+            round_statistics(stats, precision.into());
+        }
+    }
+    let summary_output = format_summary(&summary_statistics, args.format, args.legacy_json)?;
+    if args.table {
+        println!("{}", format_text_table(sequence_statistics).trim_end());
+    } else if !args.quiet {
+        println!("{}", summary_output.trim_end());
+    }
+    let summary_file = format!("summary.{}", args.format.file_extension());
+    if args.compress_summary {
+        let summary_path = args.output_path(&format!("{}.gz", summary_file));
+        let mut encoder = GzEncoder::new(BufWriter::new(File::create(&summary_path)?), Compression::default());
+        encoder.write_all(summary_output.as_bytes())?;
+        encoder.finish()?.flush()?;
+        output_files.push((summary_path, "summary"));
+    } else if !args.only_summary {
+        let summary_path = args.output_path(&summary_file);
+        fs::write(&summary_path, summary_output)?;
+        output_files.push((summary_path, "summary"));
+    }
+
+    if args.write_genome_file {
+        let genome_path = args.output_path("genome.txt");
+        fs::write(&genome_path, format_genome_file(sequence_statistics))?;
+        output_files.push((genome_path, "genome"));
+    }
+
+    if args.report_unexpected {
+        let unexpected_bases_path = args.output_path("unexpected_bases.tsv");
+        fs::write(&unexpected_bases_path, format_unexpected_bases(sequence_statistics))?;
+        output_files.push((unexpected_bases_path, "unexpected_bases"));
+    }
+
+    if args.write_fai {
+        for fasta_file in &args.fasta_files {
+            output_files.push((write_fasta_index(fasta_file)?, "fasta_index"));
+        }
+    }
+
+    if let Some(emit_fasta) = &args.emit_fasta {
+        write_converted_fasta(&args.fasta_files, emit_fasta)?;
+        output_files.push((emit_fasta.clone(), "fasta"));
+    }
+
+    if args.find_duplicates {
+        let duplicates = find_duplicate_sequences(sequence_statistics);
+        let duplicates_path = args.output_path("duplicates.json");
+        fs::write(&duplicates_path, serde_json::to_string_pretty(&duplicates)?)?;
+        output_files.push((duplicates_path, "duplicates"));
+    }
+
+    if let Some(sqlite) = &args.sqlite {
+        write_sqlite(sqlite, sequence_statistics)?;
+        output_files.push((sqlite.clone(), "sqlite"));
+    }
+
+    let assembly_statistics = compute_assembly_statistics(sequence_statistics);
+    let assembly_path = args.output_path("assembly.json");
+    fs::write(&assembly_path, serde_json::to_string_pretty(&assembly_statistics)?)?;
+    output_files.push((assembly_path, "assembly"));
+    if args.nx_curve {
+        let nx_curve_path = args.output_path("nx_curve.tsv");
+        fs::write(&nx_curve_path, format_nx_curve(&assembly_statistics))?;
+        output_files.push((nx_curve_path, "nx_curve"));
+    }
+
+    if let Some(matrix) = position_frequency_matrix {
+        let pfm_path = args.output_path("pfm.tsv");
+        fs::write(&pfm_path, format_position_frequency_matrix(matrix.lock().unwrap().counts()))?;
+        output_files.push((pfm_path, "pfm"));
+    }
+
+    if let Some(group_by_regex) = &args.group_by_regex {
+        let group_statistics = group_assembly_statistics(sequence_statistics, group_by_regex)?;
+        let groups_path = args.output_path("groups.json");
+        fs::write(&groups_path, serde_json::to_string_pretty(&group_statistics)?)?;
+        output_files.push((groups_path, "groups"));
+    }
+    Ok(output_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cli_validation() {
+        let cli = Cli::parse_from(["fastats", "does-not-exist.fasta", "-o", "output"]);
+        // Test invalid input file
+        assert!(cli.validate().is_err());
+    }
+
+    #[test]
+    fn cli_validation_stdin() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let cli = Cli::parse_from([Path::new("fastats"), Path::new("-"), Path::new("-o"), tmpdir.path()]);
+        assert!(cli.reads_from_stdin());
+        assert!(cli.validate().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn cli_validation_multiple_files() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let first_path = tmpdir.path().join("first.fasta");
+        let second_path = tmpdir.path().join("second|file.fasta");
+        fs::write(&first_path, b">seq1\nACGT\n")?;
+        fs::write(&second_path, b">seq1\nACGT\n")?;
+        let output_dir = tmpdir.path().join("output");
+
+        let cli = Cli::parse_from([Path::new("fastats"), &first_path, &second_path, Path::new("-o"), &output_dir]);
+        assert!(cli.validate().is_ok());
+        assert_eq!(cli.sequence_output_dir(&first_path), output_dir.join("first.fasta"));
+        assert_eq!(
+            cli.sequence_output_dir(&second_path),
+            output_dir.join(sanitize_filename("second|file.fasta"))
+        );
+
+        let single_cli = Cli::parse_from([Path::new("fastats"), &first_path, Path::new("-o"), &output_dir]);
+        assert_eq!(single_cli.sequence_output_dir(&first_path), output_dir);
+
+        let duplicate_cli = Cli::parse_from([Path::new("fastats"), &first_path, &first_path]);
+        assert!(duplicate_cli.validate().is_err());
+
+        let length_cli = Cli::parse_from(["fastats", "--min-length", "5", "--max-length", "5", "-"]);
+        assert!(length_cli.validate().is_ok());
+        let invalid_length_cli = Cli::parse_from(["fastats", "--min-length", "6", "--max-length", "5", "-"]);
+        assert!(invalid_length_cli.validate().is_err());
+
+        let mask_chars_cli = Cli::parse_from(["fastats", "--mask-chars", "NnXx", "-"]);
+        assert!(mask_chars_cli.validate().is_ok());
+        for invalid_mask_chars in ["", "NnA", "Nt", "Nä"] {
+            let invalid_mask_chars_cli = Cli::parse_from(["fastats", "--mask-chars", invalid_mask_chars, "-"]);
+            assert!(invalid_mask_chars_cli.validate().is_err());
+        }
+
+        let gc_cli = Cli::parse_from(["fastats", "--expect-gc-min", "0.3", "--expect-gc-max", "0.5", "-"]);
+        assert!(gc_cli.validate().is_ok());
+        for invalid_gc_args in [
+            &["--expect-gc-min", "0.6", "--expect-gc-max", "0.5"][..],
+            &["--expect-gc-max", "1.5"],
+            &["--expect-gc-min", "NaN"],
+            &["--expect-gc-per-sequence"],
+        ] {
+            let invalid_gc_cli = Cli::parse_from([&["fastats"], invalid_gc_args, &["-"]].concat());
+            assert!(invalid_gc_cli.validate().is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn expand_glob_patterns_ok() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        for file_name in ["b.fa", "a.fa", "c.fasta"] {
+            fs::write(tmpdir.path().join(file_name), b">seq1\nACGT\n")?;
+        }
+        fs::create_dir(tmpdir.path().join("d.fa"))?;
+
+        let pattern = tmpdir.path().join("*.fa");
+        let plain_path = tmpdir.path().join("c.fasta");
+        assert_eq!(
+            expand_glob_patterns(&[pattern, PathBuf::from("-"), plain_path.clone()])?,
+            vec![tmpdir.path().join("a.fa"), tmpdir.path().join("b.fa"), PathBuf::from("-"), plain_path]
+        );
+        // Plain paths are kept even if they do not exist, so that they are reported by the validation.
+        let missing_path = tmpdir.path().join("missing.fa");
+        assert_eq!(expand_glob_patterns(std::slice::from_ref(&missing_path))?, vec![missing_path]);
+
+        let error = expand_glob_patterns(&[tmpdir.path().join("*.fq")]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+        assert!(error.to_string().starts_with("No input files match the pattern"));
+        Ok(())
+    }
+
+    #[test]
+    fn cli_validation_dry_run() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let fasta_path = tmpdir.path().join("input.fasta");
+        fs::write(&fasta_path, b">seq1\nACGT\n")?;
+        let output_dir = tmpdir.path().join("output");
+
+        let cli = Cli::parse_from([Path::new("fastats"), &fasta_path, Path::new("-o"), &output_dir, Path::new("--dry-run")]);
+        assert!(cli.validate().is_ok());
+        assert!(!output_dir.exists());
+
+        let cli = Cli::parse_from([Path::new("fastats"), &fasta_path, Path::new("-o"), &output_dir]);
+        assert!(cli.validate().is_ok());
+        assert!(output_dir.exists());
+
+        let bed_dir = tmpdir.path().join("scratch").join("bed");
+        let cli = Cli::parse_from([Path::new("fastats"), &fasta_path, Path::new("--bed-dir"), &bed_dir]);
+        assert!(cli.validate().is_ok());
+        assert!(bed_dir.exists());
+        let cli = Cli::parse_from([Path::new("fastats"), &fasta_path, Path::new("--bed-dir"), &fasta_path]);
+        assert!(cli.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cli_log_level() {
+        assert_eq!(Cli::parse_from(["fastats", "-"]).log_level.level_filter(), log::LevelFilter::Error);
+        let cli = Cli::parse_from(["fastats", "--log-level", "debug", "-"]);
+        assert_eq!(cli.log_level.level_filter(), log::LevelFilter::Debug);
+        assert!(Cli::try_parse_from(["fastats", "--log-level", "trace", "-"]).is_err());
+    }
+
+    #[test]
+    fn format_summary_delimited() -> Result<(), Box<dyn Error>> {
+        let record = FastaRecord::new(
+            fasta::record::Definition::new("seq1", None),
+            fasta::record::Sequence::from(b"NACGTacgtNN".to_vec()),
+        );
+        let stats = process_fasta(None, &StatsOptions::default())(&record, 1)?.remove(0);
+
+        let tsv_output = format_summary(std::slice::from_ref(&stats), OutputFormat::Tsv, false)?;
+        let tsv_lines: Vec<&str> = tsv_output.lines().collect();
+        assert_eq!(tsv_lines.len(), 2);
+        assert!(tsv_lines[0].starts_with("sequence_name\tsource_file\tnon_masked_bases\tsoft_masked_bases\t"));
+        assert!(tsv_lines[0].contains("\tnum_gaps\tgap_lengths\t"));
+        assert!(tsv_lines[1].starts_with("seq1\t\t4\t4\t3\t"));
+        assert!(tsv_lines[1].contains("\t2\t1,2\t"));
+        assert!(tsv_lines[1].ends_with(&format!("\t{}", stats.checksum_sha256)));
+
+        let csv_output = format_summary(&[stats], OutputFormat::Csv, false)?;
+        let csv_lines: Vec<&str> = csv_output.lines().collect();
+        assert_eq!(csv_lines.len(), 2);
+        assert_eq!(csv_lines[0], tsv_lines[0].replace('\t', ","));
+        assert!(csv_lines[1].contains(",2,\"1,2\","));
+        Ok(())
+    }
+
+    #[test]
+    fn format_summary_ndjson() -> Result<(), Box<dyn Error>> {
+        let stats = [
+            compute_statistics("chr1", b"ACGTNN", &StatsOptions::default())?,
+            compute_statistics("chr2", b"acgt", &StatsOptions::default())?,
+        ];
+        let output = format_summary(&stats, OutputFormat::Ndjson, false)?;
+        assert!(output.ends_with('\n'));
+        assert!(!output.starts_with('['));
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for (line, expected) in lines.iter().zip(&stats) {
+            let value: serde_json::Value = serde_json::from_str(line)?;
+            assert_eq!(value["sequence_name"], expected.sequence_name.as_str());
+            assert_eq!(value["sequence_length"], expected.sequence_length);
+        }
+        assert_eq!(format_summary(&[], OutputFormat::Ndjson, false)?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn format_summary_json() -> Result<(), Box<dyn Error>> {
+        let stats = [compute_statistics("chr1", b"ACGTNN", &StatsOptions::default())?];
+        let report: serde_json::Value = serde_json::from_str(&format_summary(&stats, OutputFormat::Json, false)?)?;
+        assert_eq!(report["version"], SUMMARY_SCHEMA_VERSION);
+        assert_eq!(report["tool_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(report["sequences"][0]["sequence_name"], "chr1");
+
+        let legacy: serde_json::Value = serde_json::from_str(&format_summary(&stats, OutputFormat::Json, true)?)?;
+        assert_eq!(legacy, report["sequences"]);
+        Ok(())
+    }
+
+    #[test]
+    fn run_pfm() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let fasta_path = tmpdir.path().join("oligos.fasta");
+        fs::write(&fasta_path, ">oligo1\nACGT\n>oligo2\nacgA\n>oligo3\nTNGA\n")?;
+        let (output_dir_arg, fasta_arg) = (tmpdir.path().to_str().unwrap(), fasta_path.to_str().unwrap());
+        let pfm_args = ["fastats", "--quiet", "--pfm", "--no-bed-output", "-o", output_dir_arg, fasta_arg];
+        run(&Cli::try_parse_from(pfm_args)?)?;
+        assert_eq!(
+            fs::read_to_string(tmpdir.path().join("pfm.tsv"))?,
+            "position\tA\tC\tG\tT\n1\t2\t0\t0\t1\n2\t0\t2\t0\t0\n3\t0\t0\t3\t0\n4\t2\t0\t0\t1\n"
+        );
+
+        fs::write(&fasta_path, ">oligo1\nACGT\n>oligo2\nACG\n")?;
+        // Which sequence has the unexpected length depends on the order in which they are processed.
+        let error = run(&Cli::try_parse_from(pfm_args)?).unwrap_err();
+        assert_eq!(error.to_string(), "Failed to process 1 record(s).");
+        Ok(())
+    }
+
+    #[test]
+    fn format_nx_curve_ok() -> Result<(), Box<dyn Error>> {
+        let stats = [
+            compute_statistics("chr1", b"ACGTNN", &StatsOptions::default())?,
+            compute_statistics("chr2", b"ACGT", &StatsOptions::default())?,
+        ];
+        let nx_curve = format_nx_curve(&compute_assembly_statistics(&stats));
+        let lines: Vec<&str> = nx_curve.lines().collect();
+        assert_eq!(lines.len(), 102);
+        assert_eq!(lines[0], "x_percent\tnx_length");
+        assert_eq!(lines[1], "0\t6");
+        assert_eq!(lines[61], "60\t6");
+        assert_eq!(lines[62], "61\t4");
+        assert_eq!(lines[101], "100\t4");
+        Ok(())
+    }
+
+    #[test]
+    fn format_genome_file_ok() -> Result<(), Box<dyn Error>> {
+        let stats = [
+            compute_statistics("chr1", b"ACGTNN", &StatsOptions::default())?,
+            compute_statistics("chr2", b"", &StatsOptions::default())?,
+        ];
+        assert_eq!(format_genome_file(&stats), "chr1\t6\nchr2\t0\n");
+        assert_eq!(format_genome_file(&[]), "");
+        Ok(())
+    }
+
+    #[test]
+    fn format_text_table_ok() -> Result<(), Box<dyn Error>> {
+        let stats = [
+            compute_statistics("chr1", b"ACGTacNNNN", &StatsOptions::default())?,
+            compute_statistics("chrM_long_name", b"GGGC", &StatsOptions::default())?,
+        ];
+        assert_eq!(
+            format_text_table(&stats),
+            "name            length     GC%  soft%  hard%\n\
+             chr1                10   30.00  20.00  40.00\n\
+             chrM_long_name       4  100.00   0.00   0.00\n"
+        );
+        assert_eq!(format_text_table(&[]), "name  length  GC%  soft%  hard%\n");
+        Ok(())
+    }
+
+    #[test]
+    fn short_sequence_warnings_ok() -> Result<(), Box<dyn Error>> {
+        let stats = [
+            compute_statistics("chr1", b"ACGTACGTAC", &StatsOptions::default())?,
+            compute_statistics("chr2", b"ACG", &StatsOptions::default())?,
+            compute_statistics("chr3", b"", &StatsOptions::default())?,
+        ];
+        assert_eq!(short_sequence_warnings(&stats, 0), vec!["Sequence 'chr3' is empty."]);
+        assert_eq!(
+            short_sequence_warnings(&stats, 5),
+            vec!["Sequence 'chr2' is shorter than 5 bp (3 bp).", "Sequence 'chr3' is empty."]
+        );
+        assert_eq!(short_sequence_warnings(&stats, 3), vec!["Sequence 'chr3' is empty."]);
+        Ok(())
+    }
+
+    #[test]
+    fn gc_content_violations_ok() -> Result<(), Box<dyn Error>> {
+        let stats = [
+            compute_statistics("chr1", b"ACGTACGTAA", &StatsOptions::default())?,
+            compute_statistics("chr2", b"GGGC", &StatsOptions::default())?,
+            compute_statistics("chr3", b"", &StatsOptions::default())?,
+        ];
+        // The total GC content is 8/14.
+        assert!(gc_content_violations(&stats, None, None, false).is_empty());
+        assert!(gc_content_violations(&stats, Some(0.5), Some(0.6), false).is_empty());
+        assert_eq!(
+            gc_content_violations(&stats, Some(0.6), None, false),
+            vec!["The GC content of 'TOTAL' (0.5714) is outside the expected range [0.6, 1]."]
+        );
+        assert_eq!(
+            gc_content_violations(&stats, Some(0.5), Some(0.6), true),
+            vec![
+                "The GC content of 'chr1' (0.4000) is outside the expected range [0.5, 0.6].",
+                "The GC content of 'chr2' (1.0000) is outside the expected range [0.5, 0.6]."
+            ]
+        );
+        assert_eq!(gc_content_violations(&stats, None, Some(0.9), true).len(), 1);
+        assert!(gc_content_violations(&[], Some(0.5), None, false).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn format_unexpected_bases_ok() -> Result<(), Box<dyn Error>> {
+        let options = StatsOptions { ignore_iupac: true, report_unexpected: true, ..Default::default() };
+        let stats = [
+            compute_statistics("chr1", b"ACRTNN", &options)?,
+            compute_statistics("chr2", b"AC\tgx", &options)?,
+            compute_statistics("chr3", b"ACGT", &options)?,
+        ];
+        assert_eq!(
+            format_unexpected_bases(&stats),
+            "sequence_name\tposition\tbase\nchr1\t2\tR\nchr2\t2\t\\t\nchr2\t4\tx\n"
+        );
+
+        let garbage = [compute_statistics("garbage", &b"x".repeat(MAX_REPORTED_UNEXPECTED_BASES + 5), &options)?];
+        let output = format_unexpected_bases(&garbage);
+        assert_eq!(output.lines().count(), MAX_REPORTED_UNEXPECTED_BASES + 2);
+        assert!(output.ends_with("# Truncated: only the first 10000 of 10005 unexpected bases are listed.\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn write_fasta_index_ok() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let fasta_path = tmpdir.path().join("test.fasta");
+        fs::write(&fasta_path, b">seq1 desc\nACGTACGT\nACGT\n>seq2\nAAAAAA\nAAAAAA\nAA\n")?;
+
+        let index_path = write_fasta_index(&fasta_path)?;
+        assert_eq!(index_path, tmpdir.path().join("test.fasta.fai"));
+        // As reported by `samtools faidx test.fasta`.
+        assert_eq!(fs::read_to_string(index_path)?, "seq1\t12\t11\t8\t9\nseq2\t14\t31\t6\t7\n");
+
+        let gzipped_path = tmpdir.path().join("test.fasta.gz");
+        let mut encoder = flate2::write::GzEncoder::new(File::create(&gzipped_path)?, flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, b">seq1\nACGT\n")?;
+        encoder.finish()?;
+        assert_eq!(write_fasta_index(&gzipped_path).unwrap_err().kind(), ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    #[test]
+    fn write_sqlite_ok() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let database_path = tmpdir.path().join("stats.sqlite");
+        let with_source = |stats: SequenceStatistics, source_file: &str| SequenceStatistics {
+            source_file: source_file.to_string(),
+            ..stats
+        };
+        let stats = [
+            with_source(compute_statistics("chr1", b"ACGTNN", &StatsOptions::default())?, "a.fa"),
+            with_source(compute_statistics("chr2", b"GGCCa", &StatsOptions::default())?, "a.fa"),
+        ];
+        write_sqlite(&database_path, &stats)?;
+
+        // Rows with the same key are replaced, and columns of further fields are added.
+        let options = StatsOptions { dinucleotides: true, ..Default::default() };
+        let stats = [
+            with_source(compute_statistics("chr1", b"ACGTNNNN", &options)?, "a.fa"),
+            with_source(compute_statistics("chr1", b"ACG", &options)?, "b.fa"),
+        ];
+        write_sqlite(&database_path, &stats)?;
+
+        let connection = rusqlite::Connection::open(&database_path)?;
+        let rows: Vec<(String, String, i64, f64, Option<i64>)> = connection
+            .prepare(
+                "SELECT source_file, sequence_name, sequence_length, gc_content, cpg_count FROM sequence_stats \
+                 ORDER BY source_file, sequence_name",
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))?
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            rows,
+            vec![
+                ("a.fa".to_string(), "chr1".to_string(), 8, 0.25, Some(1)),
+                ("a.fa".to_string(), "chr2".to_string(), 5, 0.8, None),
+                ("b.fa".to_string(), "chr1".to_string(), 3, 2.0 / 3.0, Some(1)),
+            ]
+        );
+        let query = "SELECT gap_lengths FROM sequence_stats WHERE source_file = 'b.fa'";
+        let gap_lengths: String = connection.query_row(query, [], |row| row.get(0))?;
+        assert_eq!(gap_lengths, "[]");
+        Ok(())
+    }
+
+    #[test]
+    fn sort_statistics_ok() -> Result<(), Box<dyn Error>> {
+        let mut stats: Vec<SequenceStatistics> = [("chr10", 3), ("chr2", 1), ("chrX", 2), ("chr1", 2)]
+            .iter()
+            .map(|(name, length)| compute_statistics(name, &vec![b'A'; *length], &StatsOptions::default()))
+            .collect::<Result<_, _>>()?;
+        let names = |stats: &[SequenceStatistics]| stats.iter().map(|s| s.sequence_name.clone()).collect::<Vec<_>>();
+
+        sort_statistics(&mut stats, SortOrder::Input);
+        assert_eq!(names(&stats), ["chr10", "chr2", "chrX", "chr1"]);
+        sort_statistics(&mut stats, SortOrder::Lexical);
+        assert_eq!(names(&stats), ["chr1", "chr10", "chr2", "chrX"]);
+        sort_statistics(&mut stats, SortOrder::Natural);
+        assert_eq!(names(&stats), ["chr1", "chr2", "chr10", "chrX"]);
+        sort_statistics(&mut stats, SortOrder::Length);
+        assert_eq!(names(&stats), ["chr10", "chr1", "chrX", "chr2"]);
+        Ok(())
+    }
+
+    #[test]
+    fn create_progress_bar_quiet() {
+        assert!(create_progress_bar(true).is_hidden());
+        // Test output is captured, so stderr is no terminal.
+        assert_eq!(create_progress_bar(false).is_hidden(), !std::io::stderr().is_terminal());
+    }
+
+    /// Generates FASTA records on the fly and keeps track of how many bytes have been read.
+    struct GeneratedFasta {
+        remaining_records: usize,
+        pending: Vec<u8>,
+        bytes_read: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl std::io::Read for GeneratedFasta {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.pending.is_empty() && self.remaining_records > 0 {
+                self.remaining_records -= 1;
+                self.pending = format!(">seq{}\n{}\n", self.remaining_records, "ACGT".repeat(1000)).into_bytes();
+            }
+            let length = buf.len().min(self.pending.len());
+            buf[..length].copy_from_slice(&self.pending[..length]);
+            self.pending.drain(..length);
+            self.bytes_read.fetch_add(length, std::sync::atomic::Ordering::SeqCst);
+            Ok(length)
+        }
+    }
+
+    #[test]
+    fn run_deterministic_output() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let fasta_path = tmpdir.path().join("test.fasta");
+        let mut fasta = String::new();
+        for index in 0..300 {
+            fasta.push_str(&format!(">seq{}\n{}{}\n", index, "ACGTacgt".repeat(index % 7 + 1), "N".repeat(index % 3)));
+        }
+        fs::write(&fasta_path, fasta)?;
+        // Duplicate and overlapping regions yield records that compare equal when sorting.
+        let regions_path = tmpdir.path().join("regions.bed");
+        fs::write(&regions_path, "seq1\t0\t4\nseq1\t0\t4\nseq1\t2\t6\nseq2\t0\t8\nseq1\t0\t4\n")?;
+
+        let summary = |output_dir: &str, extra_args: &[&str]| -> Result<Vec<u8>, Box<dyn Error>> {
+            let output_dir = tmpdir.path().join(output_dir);
+            fs::create_dir_all(&output_dir)?;
+            let (output_dir_arg, fasta_arg) = (output_dir.to_str().unwrap(), fasta_path.to_str().unwrap());
+            let mut args = vec!["fastats", "--quiet", "--threads", "4", "--sort", "length", "-o", output_dir_arg];
+            args.extend_from_slice(extra_args);
+            args.push(fasta_arg);
+            let cli = Cli::try_parse_from(args)?;
+            cli.validate()?;
+            run(&cli)?;
+            Ok(fs::read(output_dir.join("summary.json"))?)
+        };
+        assert_eq!(summary("first", &[])?, summary("second", &[])?);
+        let region_args = ["--regions", regions_path.to_str().unwrap(), "--per-region"];
+        assert_eq!(summary("first_regions", &region_args)?, summary("second_regions", &region_args)?);
+        // Resuming from a previous summary (i.e., reusing all statistics) yields the same summary.
+        let previous_summary = tmpdir.path().join("first").join("summary.json");
+        let resume_args = ["--resume-from", previous_summary.to_str().unwrap()];
+        assert_eq!(fs::read(&previous_summary)?, summary("resumed", &resume_args)?);
+        Ok(())
+    }
+
+    #[test]
+    fn run_compress_summary() -> Result<(), Box<dyn Error>> {
+        use std::io::Read;
+
+        let tmpdir = tempfile::tempdir()?;
+        let fasta_path = tmpdir.path().join("test.fasta");
+        fs::write(&fasta_path, ">seq1\nACGTacgtNN\n>seq2\nGGCC\n")?;
+        let output_dir = tmpdir.path().to_str().unwrap();
+        for extra_args in [&[][..], &["--compress-summary"], &["--compress-summary", "--format", "tsv"]] {
+            let mut args = vec!["fastats", "--quiet", "-o", output_dir];
+            args.extend_from_slice(extra_args);
+            args.push(fasta_path.to_str().unwrap());
+            run(&Cli::try_parse_from(args)?)?;
+        }
+
+        let decompress = |file_name: &str| -> Result<String, std::io::Error> {
+            let mut content = String::new();
+            MultiGzDecoder::new(File::open(tmpdir.path().join(file_name))?).read_to_string(&mut content)?;
+            Ok(content)
+        };
+        let summary = fs::read_to_string(tmpdir.path().join("summary.json"))?;
+        assert_eq!(decompress("summary.json.gz")?, summary);
+        assert!(decompress("summary.tsv.gz")?.starts_with("sequence_name\t"));
+        assert!(!tmpdir.path().join("summary.tsv").exists());
+
+        // Compressed summaries can be resumed from.
+        let previous_statistics = read_summary(decompress_if_gzipped(BufReader::new(File::open(
+            tmpdir.path().join("summary.json.gz"),
+        )?))?)?;
+        assert_eq!(previous_statistics.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn run_write_manifest() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let fasta_path = tmpdir.path().join("test.fasta");
+        fs::write(&fasta_path, ">seq1\nACGTacgtNN\n>seq1\nGGCC\n>empty\n")?;
+        let output_dir = tmpdir.path().join("output");
+        let (output_dir_arg, fasta_arg) = (output_dir.to_str().unwrap(), fasta_path.to_str().unwrap());
+        let cli = Cli::try_parse_from([
+            "fastats", "--quiet", "--write-manifest", "--on-duplicate", "merge", "--gc-window", "2", "-o", output_dir_arg,
+            fasta_arg,
+        ])?;
+        cli.validate()?;
+        run(&cli)?;
+
+        let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(output_dir.join("manifest.json"))?)?;
+        let entries = manifest.as_array().unwrap();
+        let file_names: Vec<(String, &str)> = entries
+            .iter()
+            .map(|entry| {
+                let path = PathBuf::from(entry["path"].as_str().unwrap());
+                (path.file_name().unwrap().to_string_lossy().into_owned(), entry["type"].as_str().unwrap())
+            })
+            .collect();
+        let expected = [
+            ("summary.json", "summary"),
+            ("assembly.json", "assembly"),
+            ("seq1.gc.bedgraph", "bedgraph"),
+            ("seq1.non-masked.bed", "bed"),
+            ("seq1.soft-masked.bed", "bed"),
+            ("seq1.hard-masked.bed", "bed"),
+        ];
+        assert_eq!(file_names, expected.map(|(name, file_type)| (name.to_string(), file_type)));
+        for entry in entries {
+            let path = PathBuf::from(entry["path"].as_str().unwrap());
+            assert_eq!(entry["size"], fs::metadata(&path)?.len());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn run_fastq_input() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let fastq_path = tmpdir.path().join("reads.fq");
+        fs::write(&fastq_path, "@read1\nACGT\n+\nIIII\n")?;
+        let output_dir = tmpdir.path().to_str().unwrap();
+        let cli = Cli::try_parse_from(["fastats", "--quiet", "-o", output_dir, fastq_path.to_str().unwrap()])?;
+        let error = run(&cli).unwrap_err();
+        assert!(error.to_string().contains("looks like FASTQ"), "{}", error);
+        assert!(!tmpdir.path().join("summary.json").exists());
+
+        // Compressed FASTQ files are detected as well.
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"@read1\nACGT\n+\nIIII\n")?;
+        let reader = decompress_if_gzipped(std::io::Cursor::new(encoder.finish()?))?;
+        assert!(ensure_not_fastq(reader, &fastq_path).is_err());
+        assert!(ensure_not_fastq(&b">seq1\nACGT\n"[..], &fastq_path).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn run_only_summary() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let fasta_path = tmpdir.path().join("test.fasta");
+        fs::write(&fasta_path, ">seq1\nACGTacgtNN\n>seq2\nGGCC\n")?;
+        let output_dir = tmpdir.path().join("output");
+        let args = ["fastats", "--quiet", "--only-summary", "--gc-window", "2", "-o", output_dir.to_str().unwrap()];
+        let cli = Cli::try_parse_from(args.iter().chain([&fasta_path.to_str().unwrap()]))?;
+        cli.validate()?;
+        run(&cli)?;
+
+        let file_names: Vec<_> = fs::read_dir(&output_dir)?.map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(file_names, vec!["assembly.json"]);
+        let assembly: serde_json::Value = serde_json::from_str(&fs::read_to_string(output_dir.join("assembly.json"))?)?;
+        assert_eq!(assembly["num_sequences"], 2);
+        assert_eq!(assembly["total_length"], 14);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_records_streaming() -> Result<(), Box<dyn Error>> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let num_records = 500;
+        let bytes_read = std::sync::Arc::new(AtomicUsize::new(0));
+        let max_bytes_read_before_processing = AtomicUsize::new(usize::MAX);
+        let reader = fasta::io::Reader::new(BufReader::new(GeneratedFasta {
+            remaining_records: num_records,
+            pending: Vec::new(),
+            bytes_read: bytes_read.clone(),
+        }));
+
+        let options = StatsOptions::default();
+        let process_record = process_fasta(None, &options);
+        let mut read_time = Duration::ZERO;
+        let results = process_fasta_records(
+            reader,
+            |record, occurrence| {
+                max_bytes_read_before_processing.fetch_min(bytes_read.load(Ordering::SeqCst), Ordering::SeqCst);
+                process_record(record, occurrence)
+            },
+            DuplicatePolicy::Error,
+            ErrorPolicy::Continue,
+            None,
+            &mut read_time,
+        )?;
+
+        assert_eq!(results.len(), num_records);
+        assert!(results.iter().all(|result| matches!(result, Ok(stats) if stats.len() == 1)));
+        let names: Vec<String> = results.into_iter().map(|result| result.unwrap().remove(0).sequence_name).collect();
+        let input_names: Vec<String> = (0..num_records).rev().map(|i| format!("seq{}", i)).collect();
+        assert_eq!(names, input_names);
+        // The first record is processed long before the whole input has been read.
+        assert!(max_bytes_read_before_processing.load(Ordering::SeqCst) < bytes_read.load(Ordering::SeqCst) / 10);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_records_max_records() -> Result<(), Box<dyn Error>> {
+        let num_records = 500;
+        let bytes_read = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let reader = fasta::io::Reader::new(BufReader::new(GeneratedFasta {
+            remaining_records: num_records,
+            pending: Vec::new(),
+            bytes_read: bytes_read.clone(),
+        }));
+
+        let options = StatsOptions::default();
+        let process_record = process_fasta(None, &options);
+        let mut read_time = Duration::ZERO;
+        let (on_duplicate, on_error) = (DuplicatePolicy::Error, ErrorPolicy::Continue);
+        let results = process_fasta_records(reader, process_record, on_duplicate, on_error, Some(10), &mut read_time)?;
+
+        let names: Vec<String> = results.into_iter().map(|result| result.unwrap().remove(0).sequence_name).collect();
+        let input_names: Vec<String> = (num_records - 10..num_records).rev().map(|i| format!("seq{}", i)).collect();
+        assert_eq!(names, input_names);
+        // Reading stops after the last record (and the start of the next one, which ends it).
+        assert!(bytes_read.load(std::sync::atomic::Ordering::SeqCst) < 20 * 4010);
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_records_on_error() -> Result<(), Box<dyn Error>> {
+        let fasta_content = b">seq1\nACGT\n>seq2\nACXT\n>seq3\nAAAA\n>seq4\nCCCC\n";
+        let options = StatsOptions::default();
+        let process_record = process_fasta(None, &options);
+        let process = |on_error| {
+            let mut read_time = Duration::ZERO;
+            let reader = fasta::io::Reader::new(&fasta_content[..]);
+            process_fasta_records(reader, &process_record, DuplicatePolicy::Error, on_error, None, &mut read_time)
+        };
+
+        let results = process(ErrorPolicy::Continue)?;
+        assert_eq!(results.len(), 4);
+        assert!(results[1].is_err());
+        assert!(results[3].is_ok());
+
+        // With a single thread, no record is read after the failed one.
+        let results = rayon::ThreadPoolBuilder::new().num_threads(1).build()?.install(|| process(ErrorPolicy::Abort))?;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn process_fasta_records_duplicates() -> Result<(), Box<dyn Error>> {
+        let fasta_content = b">dup\nACGT\n>other\nAAAA\n>dup\nGG\n>dup\nC\n";
+        let options = StatsOptions {
+            on_duplicate: DuplicatePolicy::Merge,
+            ..Default::default()
+        };
+        let process_record = process_fasta(None, &options);
+        let occurrences = Mutex::new(Vec::new());
+        let mut read_time = Duration::ZERO;
+        let results = process_fasta_records(
+            fasta::io::Reader::new(&fasta_content[..]),
+            |record, occurrence| {
+                occurrences.lock().unwrap().push((record.sequence().as_ref().to_vec(), occurrence));
+                process_record(record, occurrence)
+            },
+            DuplicatePolicy::Merge,
+            ErrorPolicy::Continue,
+            None,
+            &mut read_time,
+        )?;
+
+        let occurrences = occurrences.into_inner().unwrap();
+        // Duplicates are processed after all other records, in input order.
+        assert_eq!(occurrences[2..], [(b"GG".to_vec(), 2), (b"C".to_vec(), 3)]);
+        assert!(occurrences.contains(&(b"ACGT".to_vec(), 1)));
+        assert!(occurrences.contains(&(b"AAAA".to_vec(), 1)));
+
+        assert!(read_time > Duration::ZERO);
+
+        // Results are still returned in input order.
+        let stats: Vec<SequenceStatistics> = results.into_iter().flat_map(|result| result.unwrap()).collect();
+        let lengths: Vec<usize> = stats.iter().map(|s| s.sequence_length).collect();
+        assert_eq!(lengths, [4, 4, 2, 1]);
+
+        let merged_stats = merge_duplicates(stats);
+        assert_eq!(merged_stats.len(), 2);
+        assert_eq!(merged_stats[0].sequence_name, "dup");
+        assert_eq!(merged_stats[0].sequence_length, 7);
+        assert_eq!(merged_stats[1].sequence_name, "other");
+        Ok(())
+    }
+
+    #[test]
+    fn open_fasta_file_gzipped() -> Result<(), Box<dyn Error>> {
+        let tmpdir = tempfile::tempdir()?;
+        let plain_path = tmpdir.path().join("test.fasta");
+        let gzipped_path = tmpdir.path().join("test.fasta.gz");
+        let fasta_content = b">seq1\nACGTacgtNN\n>seq2\nGGCC\n";
+
+        fs::write(&plain_path, fasta_content)?;
+        let mut encoder = flate2::write::GzEncoder::new(File::create(&gzipped_path)?, flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, fasta_content)?;
+        encoder.finish()?;
+        let bgzipped_path = tmpdir.path().join("test.bgzip.fasta.gz");
+        let mut writer = bgzf::io::Writer::new(File::create(&bgzipped_path)?);
+        writer.write_all(fasta_content)?;
+        writer.finish()?;
+
+        assert!(!is_bgzip_header(&fs::read(&gzipped_path)?));
+        assert!(is_bgzip_header(&fs::read(&bgzipped_path)?));
+        for path in [plain_path, gzipped_path, bgzipped_path] {
+            let mut reader = fasta::io::Reader::new(open_fasta_file(&path)?);
+            let records: Vec<FastaRecord> = reader.records().collect::<Result<_, _>>()?;
+            assert_eq!(records.len(), 2);
+            assert_eq!(records[0].name(), b"seq1");
+            assert_eq!(records[0].sequence().as_ref(), b"ACGTacgtNN");
+            assert_eq!(records[1].name(), b"seq2");
+            assert_eq!(records[1].sequence().as_ref(), b"GGCC");
+        }
+
+        // Sequences span several bgzip blocks (of at most 64 KiB each).
+        let large_path = tmpdir.path().join("large.fasta.gz");
+        let mut writer = bgzf::io::Writer::new(File::create(&large_path)?);
+        writer.write_all(b">large\n")?;
+        for _ in 0..5000 {
+            writer.write_all(b"ACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGT\n")?;
+        }
+        writer.finish()?;
+        let mut reader = fasta::io::Reader::new(open_fasta_file(&large_path)?);
+        let records: Vec<FastaRecord> = reader.records().collect::<Result<_, _>>()?;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].sequence().len(), 400_000);
+        Ok(())
+    }
+
+    #[test]
+    fn decompress_if_gzipped_pipe() -> Result<(), Box<dyn Error>> {
+        let fasta_content = b">seq1\nACGTacgtNN\n>seq2\nGGCC\n";
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(fasta_content)?;
+        let gzipped_content = encoder.finish()?;
+
+        for content in [fasta_content.to_vec(), gzipped_content] {
+            // Like stdin, a pipe cannot be rewound after peeking at its first bytes.
+            let (pipe_reader, mut pipe_writer) = std::io::pipe()?;
+            let writer_thread = std::thread::spawn(move || pipe_writer.write_all(&content));
+            let mut reader = fasta::io::Reader::new(decompress_if_gzipped(BufReader::new(pipe_reader))?);
+            let records: Vec<FastaRecord> = reader.records().collect::<Result<_, _>>()?;
+            writer_thread.join().unwrap()?;
+            assert_eq!(records.len(), 2);
+            assert_eq!(records[0].sequence().as_ref(), b"ACGTacgtNN");
+            assert_eq!(records[1].sequence().as_ref(), b"GGCC");
+        }
+        Ok(())
     }
 }